@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use serde::Deserialize;
+
+// a brisk walk, slowed down because paths aren't straight lines
+const WALKING_METERS_PER_MINUTE: f64 = 65.0;
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct Coordinates {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+#[derive(Deserialize)]
+struct Buildings {
+    buildings: HashMap<String, Coordinates>,
+}
+
+lazy_static! {
+    // without a buildings file there's nothing to check walks against
+    static ref BUILDINGS: HashMap<String, Coordinates> =
+        match std::fs::read(std::env::var("BUILDINGS").unwrap_or("./buildings.json".to_string())) {
+            Ok(bytes) => serde_json::from_slice::<Buildings>(&bytes)
+                .expect("BUILDINGS, ./buildings.json by default, is not a valid buildings file")
+                .buildings
+                .into_iter()
+                .map(|(code, coordinates)| (code.to_uppercase(), coordinates))
+                .collect(),
+            Err(_) => HashMap::new(),
+        };
+}
+
+pub fn load() {
+    lazy_static::initialize(&BUILDINGS);
+}
+
+pub fn get(code: &str) -> Option<Coordinates> {
+    BUILDINGS.get(&code.to_uppercase()).copied()
+}
+
+fn distance_meters(a: Coordinates, b: Coordinates) -> f64 {
+    let (lat_a, lat_b) = (a.lat.to_radians(), b.lat.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (b.lon - a.lon).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * h.sqrt().asin()
+}
+
+pub fn walking_minutes(a: Coordinates, b: Coordinates) -> i64 {
+    (distance_meters(a, b) / WALKING_METERS_PER_MINUTE).ceil() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{distance_meters, walking_minutes, Coordinates};
+
+    #[test]
+    fn estimates_walks_between_buildings() {
+        let gdc = Coordinates {
+            lat: 30.28625,
+            lon: -97.73662,
+        };
+        let wel = Coordinates {
+            lat: 30.28661,
+            lon: -97.73772,
+        };
+        let pai = Coordinates {
+            lat: 30.28751,
+            lon: -97.73861,
+        };
+        assert_eq!(walking_minutes(gdc, gdc), 0);
+        let short = distance_meters(gdc, wel);
+        assert!((100.0..130.0).contains(&short), "{}", short);
+        assert_eq!(walking_minutes(gdc, wel), 2);
+        assert!(walking_minutes(gdc, pai) > walking_minutes(gdc, wel));
+    }
+}
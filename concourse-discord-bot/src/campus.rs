@@ -0,0 +1,100 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use lazy_static::lazy_static;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct Term {
+    pub name: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    #[serde(default)]
+    pub holidays: Vec<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+struct Terms {
+    terms: Vec<Term>,
+}
+
+lazy_static! {
+    // catalog times are wall-clock times on campus
+    pub static ref CAMPUS_TZ: Tz = std::env::var("CAMPUS_TZ")
+        .unwrap_or("America/Chicago".to_string())
+        .parse()
+        .expect("CAMPUS_TZ is not a valid time zone name, like America/Chicago");
+    // without a terms file every day is treated as a class day
+    static ref TERMS: Vec<Term> =
+        match std::fs::read(std::env::var("TERMS").unwrap_or("./terms.json".to_string())) {
+            Ok(bytes) => serde_json::from_slice::<Terms>(&bytes)
+                .expect("TERMS, ./terms.json by default, is not a valid terms file")
+                .terms,
+            Err(_) => vec![],
+        };
+}
+
+pub fn load() {
+    lazy_static::initialize(&CAMPUS_TZ);
+    lazy_static::initialize(&TERMS);
+}
+
+pub fn now() -> DateTime<Tz> {
+    Utc::now().with_timezone(&*CAMPUS_TZ)
+}
+
+fn term_on(terms: &[Term], date: NaiveDate) -> Option<&Term> {
+    terms.iter().find(|t| t.start <= date && date <= t.end)
+}
+
+pub fn meets_on(terms: &[Term], date: NaiveDate) -> bool {
+    if terms.is_empty() {
+        return true;
+    }
+    match term_on(terms, date) {
+        Some(term) => !term.holidays.contains(&date),
+        None => false,
+    }
+}
+
+pub fn terms() -> &'static [Term] {
+    &TERMS
+}
+
+pub fn current_term(date: NaiveDate) -> Option<&'static Term> {
+    term_on(&TERMS, date)
+}
+
+// Whether classes meet on `date` at all: it's inside a term and not a holiday.
+pub fn has_classes(date: NaiveDate) -> bool {
+    meets_on(&TERMS, date)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{meets_on, Term};
+
+    #[test]
+    fn skips_holidays_and_breaks() {
+        let terms = vec![
+            Term {
+                name: "Fall 2021".to_string(),
+                start: NaiveDate::from_ymd(2021, 8, 25),
+                end: NaiveDate::from_ymd(2021, 12, 6),
+                holidays: vec![NaiveDate::from_ymd(2021, 9, 6)],
+            },
+            Term {
+                name: "Spring 2022".to_string(),
+                start: NaiveDate::from_ymd(2022, 1, 18),
+                end: NaiveDate::from_ymd(2022, 5, 2),
+                holidays: vec![],
+            },
+        ];
+        assert!(meets_on(&terms, NaiveDate::from_ymd(2021, 8, 25)));
+        assert!(!meets_on(&terms, NaiveDate::from_ymd(2021, 9, 6)));
+        assert!(!meets_on(&terms, NaiveDate::from_ymd(2021, 12, 20)));
+        assert!(meets_on(&terms, NaiveDate::from_ymd(2022, 5, 2)));
+        assert!(meets_on(&[], NaiveDate::from_ymd(2021, 12, 20)));
+    }
+}
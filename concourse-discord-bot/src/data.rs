@@ -0,0 +1,191 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{error::BotResult, friends, handlers, preferences, privacy, scheduler};
+
+// A tree keyed by user id, under the name it's exported as, how its module
+// reads a record, and how to drop a user from other users' records in it when
+// erasing them. Modules hand out their own with `store()`, or with
+// `store_in` over some other tree, which is what tests use.
+pub struct Store<'a> {
+    pub name: &'static str,
+    pub tree: &'a sled::Tree,
+    pub read: fn(&[u8]) -> BotResult<Value>,
+    pub forget: Option<fn(&sled::Tree, u64) -> BotResult<()>>,
+}
+
+// Reads a record stored as `T`, which is how most stores keep theirs.
+pub fn read_as<T: DeserializeOwned + Serialize>(bytes: &[u8]) -> BotResult<Value> {
+    Ok(serde_json::to_value(serde_json::from_slice::<T>(bytes)?)?)
+}
+
+// A new store has to be listed here for exports and erasure to cover it.
+fn stores() -> [Store<'static>; 5] {
+    [
+        handlers::store(),
+        privacy::store(),
+        preferences::store(),
+        scheduler::store(),
+        friends::store(),
+    ]
+}
+
+// Everything the bot stores about `uid`, with `null` for stores that hold
+// nothing for them.
+pub fn export(uid: u64) -> BotResult<Value> {
+    export_from(&stores(), uid)
+}
+
+fn export_from(stores: &[Store], uid: u64) -> BotResult<Value> {
+    let mut out = Map::new();
+    // ids are strings so they survive tools that read numbers as doubles
+    out.insert("user_id".to_string(), Value::String(uid.to_string()));
+    for store in stores {
+        let value = match store.tree.get(uid.to_be_bytes())? {
+            // a record that can't be read is still theirs, so it's included
+            // as it was stored
+            Some(ivec) => (store.read)(&ivec)
+                .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&ivec).into_owned())),
+            None => Value::Null,
+        };
+        out.insert(store.name.to_string(), value);
+    }
+    Ok(Value::Object(out))
+}
+
+// Removes everything stored about `uid`, including their place in other
+// users' friend lists and pending `/ccfree` requests. Returns the names of the
+// stores that held something.
+pub fn erase(uid: u64) -> BotResult<Vec<&'static str>> {
+    let mut removed = vec![];
+    if handlers::forget_free_requests(uid) {
+        removed.push("free time requests");
+    }
+    removed.extend(erase_from(&stores(), uid)?);
+    Ok(removed)
+}
+
+fn erase_from(stores: &[Store], uid: u64) -> BotResult<Vec<&'static str>> {
+    // other users' records go first, while `uid`'s own still says which
+    for store in stores {
+        if let Some(forget) = store.forget {
+            forget(store.tree, uid)?;
+        }
+    }
+    let mut removed = vec![];
+    for store in stores {
+        if store.tree.remove(uid.to_be_bytes())?.is_some() {
+            removed.push(store.name);
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{erase_from, export_from, Store};
+    use crate::{friends, handlers, preferences, privacy, scheduler};
+
+    // in-memory databases, dropped with the test, for `stores_in`
+    fn databases() -> Vec<sled::Db> {
+        (0..5)
+            .map(|_| sled::Config::new().temporary(true).open().unwrap())
+            .collect()
+    }
+
+    fn stores_in(dbs: &[sled::Db]) -> Vec<Store<'_>> {
+        vec![
+            handlers::store_in(&dbs[0]),
+            privacy::store_in(&dbs[1]),
+            preferences::store_in(&dbs[2]),
+            scheduler::store_in(&dbs[3]),
+            friends::store_in(&dbs[4]),
+        ]
+    }
+
+    #[test]
+    fn exports_every_store() {
+        let dbs = databases();
+        let stores = stores_in(&dbs);
+        let uid: u64 = 1;
+        for (store, record) in stores.iter().zip([
+            &b"[12345]"[..],
+            b"true",
+            br#"{"hidden":{"all":true}}"#,
+            b"{not json",
+            br#"{"accepted":[2],"outgoing":[],"incoming":[3]}"#,
+        ]) {
+            store.tree.insert(uid.to_be_bytes(), record).unwrap();
+        }
+
+        let export = export_from(&stores, uid).unwrap();
+        assert_eq!(export["user_id"], "1");
+        assert_eq!(export["courses"], json!([12345]));
+        // legacy records are read the way their module reads them
+        assert_eq!(export["privacy"]["level"], "private");
+        assert_eq!(export["preferences"]["hidden"]["all"], true);
+        // and one that can't be read at all is kept as it was stored
+        assert_eq!(export["notifications"], "{not json");
+        assert_eq!(export["friends"]["accepted"], json!([2]));
+        assert_eq!(export["friends"]["incoming"], json!([3]));
+
+        let empty = export_from(&stores, 2).unwrap();
+        assert_eq!(empty.as_object().unwrap().len(), stores.len() + 1);
+        assert!(stores.iter().all(|store| empty[store.name].is_null()));
+    }
+
+    #[test]
+    fn erases_every_store() {
+        let dbs = databases();
+        let stores = stores_in(&dbs);
+        let (uid, other): (u64, u64) = (1, 2);
+        for (store, record) in stores.iter().zip([
+            &b"[12345]"[..],
+            br#"{"level":"counts"}"#,
+            br#"{"hidden":{"all":true}}"#,
+            br#"{"reminder_minutes":10}"#,
+            br#"{"accepted":[2],"outgoing":[],"incoming":[3]}"#,
+        ]) {
+            store.tree.insert(uid.to_be_bytes(), record).unwrap();
+        }
+        let (courses, friends) = (stores[0].tree, stores[4].tree);
+        courses.insert(other.to_be_bytes(), b"[54321]").unwrap();
+        friends
+            .insert(
+                other.to_be_bytes(),
+                &br#"{"accepted":[1],"outgoing":[],"incoming":[3]}"#[..],
+            )
+            .unwrap();
+        friends
+            .insert(
+                3u64.to_be_bytes(),
+                &br#"{"accepted":[],"outgoing":[1,2],"incoming":[]}"#[..],
+            )
+            .unwrap();
+
+        let removed = erase_from(&stores, uid).unwrap();
+        assert_eq!(
+            removed,
+            stores.iter().map(|store| store.name).collect::<Vec<_>>()
+        );
+        for store in &stores {
+            assert!(
+                !store.tree.contains_key(uid.to_be_bytes()).unwrap(),
+                "{} still has a record",
+                store.name
+            );
+        }
+        // nobody else's record mentions them either, and the rest is kept
+        let theirs = export_from(&stores, other).unwrap();
+        assert_eq!(theirs["courses"], json!([54321]));
+        assert_eq!(theirs["friends"]["accepted"], json!([]));
+        assert_eq!(theirs["friends"]["incoming"], json!([3]));
+        assert_eq!(
+            export_from(&stores, 3).unwrap()["friends"]["outgoing"],
+            json!([2])
+        );
+        assert!(erase_from(&stores, uid).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+use std::{future::Future, time::Duration};
+
+use serenity::{
+    builder::{CreateComponents, CreateEmbed},
+    client::Context,
+    model::interactions::{
+        application_command::ApplicationCommandInteraction,
+        InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
+    },
+};
+
+use crate::{
+    error::{self, BotError, BotResult},
+    pagination, preferences,
+};
+
+const WORK_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Runs `work` on its own task so a panic or a hang in it is reported as an
+// error instead of taking the command down with it. Work that times out is
+// stopped, so it can't change anything after the user is told it failed.
+async fn run<T, F>(work: F, limit: Duration) -> BotResult<T>
+where
+    T: Send + 'static,
+    F: Future<Output = BotResult<T>> + Send + 'static,
+{
+    let mut handle = tokio::spawn(work);
+    match tokio::time::timeout(limit, &mut handle).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(why)) => Err(BotError::Internal(why.to_string())),
+        Err(_) => {
+            handle.abort();
+            Err(BotError::Timeout)
+        }
+    }
+}
+
+// Runs `work` on the blocking thread pool, for scans over every user that
+// would otherwise hold up other commands.
+pub async fn blocking<T, F>(work: F) -> BotResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> BotResult<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|why| BotError::Internal(why.to_string()))?
+}
+
+async fn finish(
+    command: &ApplicationCommandInteraction,
+    ctx: &Context,
+    result: BotResult<Vec<CreateEmbed>>,
+) -> BotResult<()> {
+    let embeds = match result {
+        Ok(embeds) => embeds,
+        Err(why) => vec![error::report(&command.data.name, &why)],
+    };
+    pagination::edit_response(command, ctx, embeds).await?;
+    Ok(())
+}
+
+async fn acknowledge(command: &ApplicationCommandInteraction, ctx: &Context) -> BotResult<()> {
+    // whether the reply is ephemeral can't be changed after deferring
+    let ephemeral = preferences::is_ephemeral(command)?;
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    if ephemeral {
+                        message.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+                    }
+                    message
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+// Acknowledges the command right away so Discord doesn't give up on it, then
+// runs `work` and edits the response with its result.
+pub async fn spawn<F>(
+    command: &ApplicationCommandInteraction,
+    ctx: &Context,
+    work: F,
+) -> BotResult<()>
+where
+    F: Future<Output = BotResult<Vec<CreateEmbed>>> + Send + 'static,
+{
+    acknowledge(command, ctx).await?;
+    let result = run(work, WORK_TIMEOUT).await;
+    finish(command, ctx, result).await
+}
+
+// Like `spawn`, for work whose reply is a single embed with its own buttons
+// rather than pages of results.
+pub async fn spawn_with_buttons<F>(
+    command: &ApplicationCommandInteraction,
+    ctx: &Context,
+    work: F,
+) -> BotResult<()>
+where
+    F: Future<Output = BotResult<(CreateEmbed, CreateComponents)>> + Send + 'static,
+{
+    acknowledge(command, ctx).await?;
+    let (embed, buttons) = match run(work, WORK_TIMEOUT).await {
+        Ok(reply) => reply,
+        Err(why) => return finish(command, ctx, Err(why)).await,
+    };
+    command
+        .edit_original_interaction_response(&ctx.http, |response| {
+            response.add_embed(embed).components(|components| {
+                *components = buttons;
+                components
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use serenity::builder::CreateEmbed;
+
+    use super::{blocking, run};
+    use crate::error::{BotError, BotResult};
+
+    #[tokio::test]
+    async fn reports_failed_work() {
+        let limit = Duration::from_millis(50);
+        let done = run(async { Ok(vec![CreateEmbed::default()]) }, limit).await;
+        assert_eq!(done.unwrap().len(), 1);
+
+        let slow: BotResult<()> = run(
+            async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(())
+            },
+            limit,
+        )
+        .await;
+        assert!(matches!(slow, Err(BotError::Timeout)));
+
+        let panicked: BotResult<()> = run(async { panic!("bad record") }, limit).await;
+        assert!(matches!(panicked, Err(BotError::Internal(_))));
+    }
+
+    #[tokio::test]
+    async fn stops_work_that_timed_out() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let flag = finished.clone();
+        let slow: BotResult<()> = run(
+            async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                flag.store(true, Ordering::SeqCst);
+                Ok(())
+            },
+            Duration::from_millis(20),
+        )
+        .await;
+        assert!(matches!(slow, Err(BotError::Timeout)));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!finished.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn reports_failed_blocking_work() {
+        assert_eq!(blocking(|| Ok(1)).await.unwrap(), 1);
+        let panicked: Result<(), _> = blocking(|| panic!("bad record")).await;
+        assert!(matches!(panicked, Err(BotError::Internal(_))));
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+
+use serenity::{
+    builder::CreateEmbed,
+    client::Context,
+    model::interactions::{
+        application_command::ApplicationCommandInteraction,
+        message_component::MessageComponentInteraction,
+        InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
+    },
+    utils::Color,
+};
+
+#[derive(Debug)]
+pub enum BotError {
+    // a sled tree couldn't be read or written, or held a corrupt record
+    Storage(String),
+    Discord(serenity::Error),
+    // the command was used incorrectly; the message is shown to the user
+    Input(String),
+    Timeout,
+    // the work behind a deferred command panicked
+    Internal(String),
+    // a data file the command needs is missing or couldn't be parsed
+    Catalog(String),
+}
+
+pub type BotResult<T> = Result<T, BotError>;
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotError::Storage(why) => write!(f, "storage error: {}", why),
+            BotError::Discord(why) => write!(f, "discord error: {}", why),
+            BotError::Input(why) => write!(f, "input error: {}", why),
+            BotError::Timeout => write!(f, "timed out"),
+            BotError::Internal(why) => write!(f, "internal error: {}", why),
+            BotError::Catalog(why) => write!(f, "catalog error: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for BotError {}
+
+impl From<sled::Error> for BotError {
+    fn from(why: sled::Error) -> Self {
+        BotError::Storage(why.to_string())
+    }
+}
+
+impl From<serde_json::Error> for BotError {
+    fn from(why: serde_json::Error) -> Self {
+        BotError::Storage(format!("corrupt record: {}", why))
+    }
+}
+
+impl From<serenity::Error> for BotError {
+    fn from(why: serenity::Error) -> Self {
+        BotError::Discord(why)
+    }
+}
+
+impl BotError {
+    pub fn embed(&self) -> CreateEmbed {
+        let (title, description) = match self {
+            BotError::Input(why) => ("Incorrect Command Usage", why.as_str()),
+            BotError::Storage(_) => ("Storage Error", "Your data couldn't be read or saved. Try again later, and report this to the developer (check bot's about) if it keeps happening."),
+            BotError::Discord(_) => ("Discord Error", "Discord didn't accept part of this command. Make sure the bot has the permissions it needs and try again."),
+            BotError::Timeout => ("Something Went Wrong", "This command took too long to finish. Try again later."),
+            BotError::Internal(_) => ("Something Went Wrong", "This command ran into a problem. Try again later."),
+            BotError::Catalog(_) => ("Data Unavailable", "The bot couldn't load the data this command needs. Report this to the developer (check bot's about)."),
+        };
+        let mut embed = CreateEmbed::default();
+        embed
+            .title(title)
+            .description(description)
+            .color(Color::from_rgb(255, 0, 0));
+        embed
+    }
+}
+
+// Logs an error from `source` (a command name or component id) and renders
+// it for the user.
+pub fn report(source: &str, why: &BotError) -> CreateEmbed {
+    println!("Error in {}: {}", source, why);
+    why.embed()
+}
+
+pub async fn respond(command: &ApplicationCommandInteraction, ctx: &Context, why: BotError) {
+    let embed = report(&command.data.name, &why);
+    let created = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    message
+                        .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                        .add_embed(embed.clone())
+                })
+        })
+        .await;
+    // the command may have been acknowledged before it failed
+    if created.is_err() {
+        if let Err(why) = command
+            .edit_original_interaction_response(&ctx.http, |response| response.add_embed(embed))
+            .await
+        {
+            println!("Cannot respond to slash command: {}", why);
+        }
+    }
+}
+
+pub async fn respond_component(
+    component: &MessageComponentInteraction,
+    ctx: &Context,
+    why: BotError,
+) {
+    let embed = report(&component.data.custom_id, &why);
+    if let Err(why) = component
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    message
+                        .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                        .add_embed(embed)
+                })
+        })
+        .await
+    {
+        println!("Cannot respond to component: {}", why);
+    }
+}
@@ -0,0 +1,130 @@
+use chrono::{NaiveTime, Timelike, Weekday};
+use svg::Document;
+use svg_schedule::ui::{
+    render_command_text, render_free_window, render_horizontal_lines, render_name_text,
+    render_vertical_lines, render_weekday_texts, VIEWPORT_HEIGHT, VIEWPORT_WIDTH,
+};
+
+// the rendered schedule only has room for the school week
+pub const WEEKDAYS: [Weekday; 5] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+];
+
+pub type Window = (NaiveTime, NaiveTime);
+
+pub fn day_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+// Gaps of at least `min_minutes` between the busy times on each weekday,
+// looking only inside `between`.
+pub fn windows(
+    busy: &[(Weekday, NaiveTime, NaiveTime)],
+    between: Window,
+    min_minutes: i64,
+) -> Vec<(Weekday, Vec<Window>)> {
+    WEEKDAYS
+        .iter()
+        .map(|day| {
+            let mut taken: Vec<Window> = busy
+                .iter()
+                .filter(|(d, start, end)| d == day && *start < between.1 && *end > between.0)
+                .map(|(_, start, end)| (*start.max(&between.0), *end.min(&between.1)))
+                .collect();
+            taken.sort();
+            let mut free = vec![];
+            let mut cursor = between.0;
+            for (start, end) in taken {
+                if start > cursor {
+                    free.push((cursor, start));
+                }
+                cursor = cursor.max(end);
+            }
+            if cursor < between.1 {
+                free.push((cursor, between.1));
+            }
+            free.retain(|(start, end)| (*end - *start).num_minutes() >= min_minutes);
+            (*day, free)
+        })
+        .collect()
+}
+
+// The `count` longest windows, back in the order they happen.
+pub fn longest(mut windows: Vec<Window>, count: usize) -> Vec<Window> {
+    windows.sort_by_key(|(start, end)| (std::cmp::Reverse(*end - *start), *start));
+    windows.truncate(count);
+    windows.sort();
+    windows
+}
+
+pub fn render(title: &str, windows: &[(Weekday, Vec<Window>)]) -> String {
+    let mut doc = Document::new().set(
+        "viewBox",
+        (0, 0, VIEWPORT_WIDTH as i32, VIEWPORT_HEIGHT as i32),
+    );
+    doc = render_command_text(doc, "/ccfree");
+    doc = render_name_text(doc, title);
+    doc = render_weekday_texts(doc);
+    doc = render_horizontal_lines(doc);
+    doc = render_vertical_lines(doc);
+    for (day, day_windows) in windows {
+        for (start, end) in day_windows {
+            let minutes = |t: &NaiveTime| (t.hour() * 60 + t.minute()) as i32;
+            doc = render_free_window(
+                (
+                    day.num_days_from_monday() as i32,
+                    minutes(start),
+                    minutes(end),
+                ),
+                doc,
+            );
+        }
+    }
+    doc.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveTime, Weekday};
+
+    use super::{longest, windows};
+
+    #[test]
+    fn finds_common_gaps() {
+        let t = |h, m| NaiveTime::from_hms(h, m, 0);
+        let busy = vec![
+            (Weekday::Mon, t(9, 0), t(10, 0)),
+            (Weekday::Mon, t(9, 30), t(11, 0)),
+            (Weekday::Mon, t(11, 20), t(12, 0)),
+            (Weekday::Mon, t(16, 0), t(19, 0)),
+            (Weekday::Tue, t(7, 0), t(8, 30)),
+        ];
+        let found = windows(&busy, (t(8, 0), t(17, 0)), 30);
+        assert_eq!(found.len(), 5);
+        assert_eq!(found[0].1, vec![(t(8, 0), t(9, 0)), (t(12, 0), t(16, 0))]);
+        assert_eq!(found[1].1, vec![(t(8, 30), t(17, 0))]);
+        assert_eq!(found[2].1, vec![(t(8, 0), t(17, 0))]);
+
+        let gaps = vec![
+            (t(8, 0), t(9, 0)),
+            (t(10, 0), t(13, 0)),
+            (t(14, 0), t(16, 0)),
+        ];
+        assert_eq!(
+            longest(gaps, 2),
+            vec![(t(10, 0), t(13, 0)), (t(14, 0), t(16, 0))]
+        );
+    }
+}
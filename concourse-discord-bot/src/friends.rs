@@ -0,0 +1,179 @@
+use std::{collections::BTreeSet, sync::Mutex};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::{data, error::BotResult};
+
+lazy_static! {
+    static ref FRIENDDB: sled::Db =
+        sled::open(std::env::var("FRIENDDB").unwrap_or("./friends.db".to_string()))
+            .expect("Expected to open the FRIENDDB database, ./friends.db by default");
+    // every change touches both users' records
+    static ref LOCK: Mutex<()> = Mutex::new(());
+}
+
+pub fn load() {
+    lazy_static::initialize(&FRIENDDB);
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+pub struct Friends {
+    pub accepted: BTreeSet<u64>,
+    // requests this user sent that haven't been answered yet
+    pub outgoing: BTreeSet<u64>,
+    pub incoming: BTreeSet<u64>,
+}
+
+impl Friends {
+    fn is_empty(&self) -> bool {
+        self.accepted.is_empty() && self.outgoing.is_empty() && self.incoming.is_empty()
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Request {
+    Sent,
+    // the other user had already asked
+    Accepted,
+    AlreadySent,
+    AlreadyFriends,
+}
+
+fn send(from: (u64, &mut Friends), to: (u64, &mut Friends)) -> Request {
+    let ((from_id, from), (to_id, to)) = (from, to);
+    if from.accepted.contains(&to_id) {
+        return Request::AlreadyFriends;
+    }
+    if from.incoming.remove(&to_id) {
+        to.outgoing.remove(&from_id);
+        from.accepted.insert(to_id);
+        to.accepted.insert(from_id);
+        return Request::Accepted;
+    }
+    if !from.outgoing.insert(to_id) {
+        return Request::AlreadySent;
+    }
+    to.incoming.insert(from_id);
+    Request::Sent
+}
+
+// Ends a friendship or drops a pending request in either direction.
+fn unlink(a: (u64, &mut Friends), b: (u64, &mut Friends)) -> bool {
+    let ((a_id, a), (b_id, b)) = (a, b);
+    let mut removed = false;
+    for (x, y_id) in [(&mut *a, b_id), (&mut *b, a_id)] {
+        removed |= x.accepted.remove(&y_id);
+        removed |= x.outgoing.remove(&y_id);
+        removed |= x.incoming.remove(&y_id);
+    }
+    removed
+}
+
+pub fn store() -> data::Store<'static> {
+    store_in(&FRIENDDB)
+}
+
+pub fn store_in(tree: &sled::Tree) -> data::Store<'_> {
+    data::Store {
+        name: "friends",
+        tree,
+        read: data::read_as::<Friends>,
+        forget: Some(forget),
+    }
+}
+
+pub fn get(uid: u64) -> BotResult<Friends> {
+    get_in(&FRIENDDB, uid)
+}
+
+fn get_in(tree: &sled::Tree, uid: u64) -> BotResult<Friends> {
+    match tree.get(uid.to_be_bytes())? {
+        Some(ivec) => Ok(serde_json::from_slice(&ivec)?),
+        None => Ok(Friends::default()),
+    }
+}
+
+fn put(uid: u64, friends: &Friends) -> BotResult<()> {
+    put_in(&FRIENDDB, uid, friends)
+}
+
+fn put_in(tree: &sled::Tree, uid: u64, friends: &Friends) -> BotResult<()> {
+    match friends.is_empty() {
+        true => tree.remove(uid.to_be_bytes())?,
+        false => tree.insert(uid.to_be_bytes(), serde_json::to_vec(friends)?)?,
+    };
+    Ok(())
+}
+
+fn update<T>(
+    a: u64,
+    b: u64,
+    f: impl FnOnce((u64, &mut Friends), (u64, &mut Friends)) -> T,
+) -> BotResult<T> {
+    let _guard = LOCK.lock().unwrap();
+    let (mut a_friends, mut b_friends) = (get(a)?, get(b)?);
+    let out = f((a, &mut a_friends), (b, &mut b_friends));
+    put(a, &a_friends)?;
+    put(b, &b_friends)?;
+    Ok(out)
+}
+
+pub fn request(from: u64, to: u64) -> BotResult<Request> {
+    update(from, to, send)
+}
+
+// Accepts a pending request from `from`. Returns false if there wasn't one.
+pub fn accept(uid: u64, from: u64) -> BotResult<bool> {
+    update(uid, from, |me, them| match me.1.incoming.contains(&from) {
+        true => send(me, them) == Request::Accepted,
+        false => false,
+    })
+}
+
+pub fn remove(uid: u64, other: u64) -> BotResult<bool> {
+    update(uid, other, unlink)
+}
+
+// Drops `uid` from the records in `tree` of everyone they're friends with or
+// have a request with. Their own record is left for the caller to remove.
+fn forget(tree: &sled::Tree, uid: u64) -> BotResult<()> {
+    let _guard = LOCK.lock().unwrap();
+    let mine = get_in(tree, uid)?;
+    for other in mine
+        .accepted
+        .iter()
+        .chain(&mine.outgoing)
+        .chain(&mine.incoming)
+    {
+        let mut theirs = get_in(tree, *other)?;
+        unlink((uid, &mut Friends::default()), (*other, &mut theirs));
+        put_in(tree, *other, &theirs)?;
+    }
+    Ok(())
+}
+
+pub fn are_friends(a: u64, b: u64) -> BotResult<bool> {
+    Ok(get(a)?.accepted.contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{send, unlink, Friends, Request};
+
+    #[test]
+    fn requires_both_sides() {
+        let (mut a, mut b) = (Friends::default(), Friends::default());
+        assert_eq!(send((1, &mut a), (2, &mut b)), Request::Sent);
+        assert_eq!(send((1, &mut a), (2, &mut b)), Request::AlreadySent);
+        assert!(a.accepted.is_empty() && b.incoming.contains(&1));
+
+        assert_eq!(send((2, &mut b), (1, &mut a)), Request::Accepted);
+        assert!(a.accepted.contains(&2) && b.accepted.contains(&1));
+        assert!(a.outgoing.is_empty() && b.incoming.is_empty());
+        assert_eq!(send((1, &mut a), (2, &mut b)), Request::AlreadyFriends);
+
+        assert!(unlink((2, &mut b), (1, &mut a)));
+        assert_eq!((a, b), (Friends::default(), Friends::default()));
+    }
+}
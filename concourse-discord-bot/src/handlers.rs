@@ -1,25 +1,75 @@
 use std::{
+    borrow::Cow,
     collections::{HashMap, HashSet},
-    io::Read,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
-use chrono::{DateTime, Utc};
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
 use lazy_static::lazy_static;
 use serde::Deserialize;
+use serde_json::Value;
 use serenity::{
-    builder::CreateEmbed,
+    builder::{CreateComponents, CreateEmbed},
     client::Context,
+    futures::{stream, StreamExt},
+    http::{AttachmentType, Http, StatusCode},
     model::{
-        interactions::application_command::{
-            ApplicationCommandInteraction, ApplicationCommandInteractionDataOptionValue,
+        guild::PartialMember,
+        id::{ChannelId, GuildId, UserId},
+        interactions::{
+            application_command::{
+                ApplicationCommandInteraction, ApplicationCommandInteractionDataOptionValue,
+                ResolvedTarget,
+            },
+            autocomplete::AutocompleteInteraction,
+            message_component::{ButtonStyle, MessageComponentInteraction},
+            InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
         },
         prelude::RoleId,
+        user::User,
     },
     utils::Color,
 };
-use sled;
 
-#[derive(Deserialize, PartialEq, Debug)]
+use crate::{
+    buildings, campus, data, deferred,
+    error::{BotError, BotResult},
+    free,
+    friends::{self, Request},
+    ics, preferences,
+    privacy::{self, Level, Viewer, Visibility},
+    scheduler,
+    search::SearchIndex,
+};
+
+const MAX_CODES: usize = 10;
+// member lookups a command has in flight at once
+const MEMBER_LOOKUPS: usize = 10;
+// how long a member lookup is trusted before asking Discord again
+const MEMBERSHIP_EXPIRY: Duration = Duration::from_secs(5 * 60);
+// friends used in place of picked users, as many as `/ccfree` has user options
+const MAX_FRIENDS: usize = 5;
+const MAX_SEARCH_RESULTS: usize = 100;
+// Discord rejects autocomplete choice names and values longer than this
+const MAX_CHOICE_LENGTH: usize = 100;
+// longest break, in minutes, between two classes in the same building that
+// still counts as adjacent
+const ADJACENT_GAP: i64 = 90;
+// classes this close together are worth pointing out even across buildings
+const BACK_TO_BACK: i64 = 15;
+const FREE_WINDOWS_PER_DAY: usize = 3;
+// far enough ahead to get past a winter or summer break
+const LOOKAHEAD_DAYS: i64 = 150;
+const DEFAULT_REMINDER_MINUTES: i64 = 10;
+const DEFAULT_FREE_MINUTES: i64 = 30;
+// the buttons on a `/ccfree` reply stop working with its interaction token
+const FREE_REQUEST_EXPIRY: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Deserialize, PartialEq, Eq, Hash, Debug)]
 struct CourseTime {
     day: Option<String>,
     time: (DateTime<Utc>, DateTime<Utc>),
@@ -27,6 +77,7 @@ struct CourseTime {
 }
 
 #[derive(Deserialize, Debug)]
+#[allow(dead_code)]
 struct CourseData {
     code: i64,
     link: Option<String>,
@@ -48,11 +99,34 @@ struct ServerRoles {
     servers: HashMap<u64, HashMap<String, u64>>,
 }
 
+#[derive(Clone)]
+struct FreeRequest {
+    owner: u64,
+    users: Vec<u64>,
+    // private users who pressed "Include my schedule"
+    consented: HashSet<u64>,
+    min_minutes: i64,
+    between: free::Window,
+    render: bool,
+    ephemeral: bool,
+    channel: ChannelId,
+    // where the command was run, and which users were picked from its
+    // members, for privacy checks
+    guild: Option<u64>,
+    members: HashSet<u64>,
+    expires: Instant,
+}
+
+struct FreeSummary {
+    embed: CreateEmbed,
+    included: usize,
+    excluded: Vec<u64>,
+    windows: Vec<(Weekday, Vec<free::Window>)>,
+}
+
 lazy_static! {
     static ref USERDB: sled::Db =
-        { sled::open(std::env::var("USERDB").unwrap_or("./user.db".to_string())).unwrap() };
-    static ref PRIVACYDB: sled::Db =
-        { sled::open(std::env::var("PRIVACYDB").unwrap_or("./privacy.db".to_string())).unwrap() };
+        sled::open(std::env::var("USERDB").unwrap_or("./user.db".to_string())).unwrap();
     static ref COURSEDATA: HashMap<i64, CourseData> = {
         let courses: Courses = serde_json::from_slice(
             &std::fs::read(std::env::var("COURSEDATA").unwrap_or("./courses.json".to_string()))
@@ -65,442 +139,2869 @@ lazy_static! {
         }
         map
     };
-    static ref ROLEMAPPING: ServerRoles = {
-        let mappings: ServerRoles = serde_json::from_slice(
-            &std::fs::read(std::env::var("ROLEMAPPING").unwrap_or("./roles.json".to_string()))
-                .unwrap(),
-        )
-        .unwrap();
-        mappings
+    static ref SEARCHINDEX: SearchIndex = {
+        let mut index = SearchIndex::default();
+        for course in COURSEDATA.values() {
+            index.add(course.code, &course.code.to_string());
+            for field in [&course.name, &course.instructor, &course.instruction_mode] {
+                index.add(course.code, field.as_deref().unwrap_or(""));
+            }
+            for flag in &course.flags {
+                index.add(course.code, flag);
+            }
+            for time in &course.times {
+                index.add(course.code, time.location.as_deref().unwrap_or(""));
+            }
+        }
+        index
     };
+    // kept as an error so only `/ccrole` fails when the file is broken
+    static ref ROLEMAPPING: Result<ServerRoles, String> = {
+        let path = std::env::var("ROLEMAPPING").unwrap_or("./roles.json".to_string());
+        std::fs::read(&path)
+            .map_err(|why| why.to_string())
+            .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|why| why.to_string()))
+            .map_err(|why| format!("{}: {}", path, why))
+    };
+    static ref FREEREQUESTS: Mutex<HashMap<u64, FreeRequest>> = Mutex::new(HashMap::new());
+    // whether each (server, user) is a member, and until when that holds
+    static ref MEMBERSHIP: Mutex<HashMap<(u64, u64), (bool, Instant)>> = Mutex::new(HashMap::new());
 }
 
-fn is_private(uid: u64) -> bool {
-    match PRIVACYDB.get(uid.to_be_bytes()) {
-        Ok(Some(ivec)) => String::from_utf8(ivec.to_vec()).unwrap().parse().unwrap(),
-        _ => false,
+pub fn load_catalog() {
+    lazy_static::initialize(&USERDB);
+    lazy_static::initialize(&COURSEDATA);
+    lazy_static::initialize(&SEARCHINDEX);
+    if let Err(why) = &*ROLEMAPPING {
+        println!("Class roles are unavailable: {}", why);
     }
 }
 
-pub fn ccupdate<'a>(
-    embed: &'a mut CreateEmbed,
-    command: &ApplicationCommandInteraction,
-) -> &'a mut CreateEmbed {
-    let options = command
+fn get_option<'a>(
+    command: &'a ApplicationCommandInteraction,
+    name: &str,
+) -> Option<&'a ApplicationCommandInteractionDataOptionValue> {
+    command
         .data
         .options
-        .get(0)
-        .expect("Expected codes option")
-        .resolved
-        .as_ref()
-        .expect("Expected string value");
-    if let ApplicationCommandInteractionDataOptionValue::String(codes) = options {
-        let codes: Vec<i64> = codes
-            .split(",")
-            .map(|s| s.trim())
-            .filter_map(|s| s.parse().ok())
-            .collect();
-        USERDB
-            .insert(
-                command.user.id.as_u64().to_be_bytes(),
-                serde_json::to_vec(&codes[..(10.min(codes.len()))]).unwrap(),
-            )
-            .unwrap();
-        embed
-            .title("Success")
-            .description("Use the `ccuser` command to see your schedule.")
-            .color(Color::from_rgb(0, 255, 0));
-        return embed;
+        .iter()
+        .find(|o| o.name == name)
+        .and_then(|o| o.resolved.as_ref())
+}
+
+fn require_option<'a>(
+    command: &'a ApplicationCommandInteraction,
+    name: &str,
+) -> BotResult<&'a ApplicationCommandInteractionDataOptionValue> {
+    get_option(command, name)
+        .ok_or_else(|| BotError::Input(format!("The `{}` option is required.", name)))
+}
+
+pub fn store() -> data::Store<'static> {
+    store_in(&USERDB)
+}
+
+pub fn store_in(tree: &sled::Tree) -> data::Store<'_> {
+    data::Store {
+        name: "courses",
+        tree,
+        read: data::read_as::<Vec<i64>>,
+        forget: None,
     }
-    unknown_command(embed, command)
 }
 
-pub fn ccuser<'a>(
-    embed: &'a mut CreateEmbed,
-    command: &ApplicationCommandInteraction,
-) -> &'a mut CreateEmbed {
-    let options = command
-        .data
-        .options
-        .get(0)
-        .expect("Expected user option")
-        .resolved
-        .as_ref()
-        .expect("Expected user value");
-    let issuer_course_codes: Vec<i64> = match USERDB.get(command.user.id.as_u64().to_be_bytes()) {
-        Ok(Some(ivec)) => serde_json::from_slice(&ivec).unwrap_or(vec![]),
-        _ => vec![],
-    };
-    let issuer_courses: Vec<&CourseData> = issuer_course_codes
-        .iter()
-        .filter_map(|c| COURSEDATA.get(c))
-        .collect();
-    let mut issuer_times = vec![];
-    for course in &issuer_courses {
-        for time in &course.times {
-            issuer_times.push(time);
-        }
+fn get_user_codes(uid: u64) -> BotResult<Vec<i64>> {
+    match USERDB.get(uid.to_be_bytes())? {
+        Some(ivec) => Ok(serde_json::from_slice(&ivec)?),
+        None => Ok(vec![]),
     }
-    if let ApplicationCommandInteractionDataOptionValue::User(user, Some(member)) = options {
-        if is_private(*user.id.as_u64()) && command.user.id != user.id {
-            // allow ccuser if issuer == target
-            embed
-                .title(member.nick.as_ref().unwrap_or(&user.name))
-                .description("This user's data is private.")
-                .color(Color::from_rgb(255, 85, 0));
-            return embed;
+}
+
+fn store_user_codes(uid: u64, codes: &[i64]) -> BotResult<()> {
+    USERDB.insert(
+        uid.to_be_bytes(),
+        serde_json::to_vec(&codes[..(MAX_CODES.min(codes.len()))])?,
+    )?;
+    Ok(())
+}
+
+fn format_time(time: &CourseTime) -> String {
+    format!(
+        "{} | {}-{} | {}",
+        time.day.as_ref().unwrap_or(&String::from("-")),
+        time.time.0.format("%I:%M %p"),
+        time.time.1.format("%I:%M %p"),
+        time.location.as_ref().unwrap_or(&String::from("-"))
+    )
+}
+
+fn course_days(day: &str) -> Vec<Weekday> {
+    let mut days = vec![];
+    let mut chars = day.chars().map(|c| c.to_ascii_uppercase()).peekable();
+    while let Some(c) = chars.next() {
+        let weekday = match c {
+            'M' => Weekday::Mon,
+            'T' if chars.peek() == Some(&'H') => {
+                chars.next();
+                Weekday::Thu
+            }
+            'T' => Weekday::Tue,
+            'W' => Weekday::Wed,
+            'F' => Weekday::Fri,
+            'S' if chars.peek() == Some(&'U') => {
+                chars.next();
+                Weekday::Sun
+            }
+            'S' => Weekday::Sat,
+            _ => continue,
+        };
+        days.push(weekday);
+    }
+    days
+}
+
+fn times_overlap(a: &CourseTime, b: &CourseTime) -> bool {
+    let a_days = a.day.as_deref().map(course_days).unwrap_or_default();
+    let b_days = b.day.as_deref().map(course_days).unwrap_or_default();
+    a_days.iter().any(|d| b_days.contains(d))
+        && a.time.0.time() < b.time.1.time()
+        && b.time.0.time() < a.time.1.time()
+}
+
+fn find_conflicts(codes: &[i64]) -> Vec<String> {
+    let courses: Vec<&CourseData> = codes.iter().filter_map(|c| COURSEDATA.get(c)).collect();
+    conflicts_between(&courses)
+}
+
+fn conflicts_between(courses: &[&CourseData]) -> Vec<String> {
+    let mut conflicts = vec![];
+    for (i, a) in courses.iter().enumerate() {
+        for b in &courses[i + 1..] {
+            for a_time in &a.times {
+                for b_time in b.times.iter().filter(|t| times_overlap(a_time, t)) {
+                    conflicts.push(format!(
+                        "`{}` {} overlaps `{}` {}",
+                        a.code,
+                        format_time(a_time),
+                        b.code,
+                        format_time(b_time)
+                    ));
+                }
+            }
         }
-        if let Ok(Some(courses_bytes)) = USERDB.get(user.id.as_u64().to_be_bytes()) {
-            let target_courses: Vec<i64> = serde_json::from_slice(&courses_bytes).unwrap_or(vec![]);
-            let target_courses: Vec<&CourseData> = target_courses
-                .iter()
-                .filter_map(|c| COURSEDATA.get(c))
-                .collect();
-            embed
-                .title(member.nick.as_ref().unwrap_or(&user.name))
-                .color(Color::from_rgb(0, 255, 0));
-            for course in target_courses {
-                let course_code_matches = issuer_course_codes.contains(&course.code);
-                let key = match course_code_matches && command.user.id != user.id {
-                    true => format!(
-                        "__**{}: {}**__",
-                        course.code,
-                        course
-                            .name
-                            .as_ref()
-                            .unwrap_or(&String::from("Unknown Name"))
-                    ),
-                    false => format!(
-                        "**{}: {}**",
-                        course.code,
-                        course
-                            .name
-                            .as_ref()
-                            .unwrap_or(&String::from("Unknown Name"))
-                    ),
-                };
-                let values: Vec<String> = course
-                    .times
-                    .iter()
-                    .map(|t| {
-                        match issuer_times.contains(&t)
-                            && command.user.id != user.id
-                            && !course_code_matches
-                        {
-                            true => format!(
-                                "__{} | {}-{} | {}__",
-                                t.day.as_ref().unwrap_or(&String::from("-")),
-                                t.time.0.format("%I:%M %p"),
-                                t.time.1.format("%I:%M %p"),
-                                t.location.as_ref().unwrap_or(&String::from("-"))
-                            ),
-                            false => format!(
-                                "{} | {}-{} | {}",
-                                t.day.as_ref().unwrap_or(&String::from("-")),
-                                t.time.0.format("%I:%M %p"),
-                                t.time.1.format("%I:%M %p"),
-                                t.location.as_ref().unwrap_or(&String::from("-"))
-                            ),
-                        }
-                    })
+    }
+    conflicts
+}
+
+// Splits a location into its building code and room number, so "GDC 2.216",
+// "gdc 2.216" and "GDC2216" all come out as ("GDC", "2216"). The room is
+// empty when only a building is given.
+fn location_key(location: &str) -> Option<(String, String)> {
+    let compact: String = location
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let split = compact
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(compact.len());
+    let (building, room) = compact.split_at(split);
+    match building.is_empty() {
+        true => None,
+        false => Some((building.to_string(), room.to_string())),
+    }
+}
+
+fn building(location: &str) -> Option<String> {
+    location_key(location).map(|(building, _)| building)
+}
+
+fn days_label(days: &[Weekday]) -> String {
+    days.iter()
+        .map(|d| match d {
+            Weekday::Mon => "M",
+            Weekday::Tue => "T",
+            Weekday::Wed => "W",
+            Weekday::Thu => "TH",
+            Weekday::Fri => "F",
+            Weekday::Sat => "S",
+            Weekday::Sun => "SU",
+        })
+        .collect()
+}
+
+// Meetings of one person that lead into a meeting of the other on the same
+// day, either in the same building or right after each other.
+fn adjacencies(mine: &[&CourseTime], theirs: &[&CourseTime], name: &str) -> Vec<String> {
+    let mut lines = vec![];
+    for a in mine {
+        for b in theirs {
+            for (first, second, first_name, second_name) in
+                [(a, b, "you", name), (b, a, name, "you")]
+            {
+                let first_days = first.day.as_deref().map(course_days).unwrap_or_default();
+                let days: Vec<Weekday> = second
+                    .day
+                    .as_deref()
+                    .map(course_days)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|d| first_days.contains(d))
                     .collect();
-                let value = values.join("\n");
-                if values.len() > 0 {
-                    embed.field(key, value, false);
-                } else {
-                    embed.field(key, "No times", false);
+                let gap = (second.time.0.time() - first.time.1.time()).num_minutes();
+                if days.is_empty() || !(0..=ADJACENT_GAP).contains(&gap) {
+                    continue;
+                }
+                let first_building = first.location.as_deref().and_then(building);
+                let second_building = second.location.as_deref().and_then(building);
+                match (first_building, second_building) {
+                    (Some(x), Some(y)) if x == y => lines.push(format!(
+                        "You both have classes in {} on {} at {} ({}) then {} ({})",
+                        x,
+                        days_label(&days),
+                        first.time.0.format("%I:%M %p"),
+                        first_name,
+                        second.time.0.format("%I:%M %p"),
+                        second_name
+                    )),
+                    (x, y) if gap <= BACK_TO_BACK => lines.push(format!(
+                        "Back to back on {}: {} in {} until {}, then {} in {} at {}",
+                        days_label(&days),
+                        first_name,
+                        x.unwrap_or_else(|| "-".to_string()),
+                        first.time.1.format("%I:%M %p"),
+                        second_name,
+                        y.unwrap_or_else(|| "-".to_string()),
+                        second.time.0.format("%I:%M %p")
+                    )),
+                    _ => {}
                 }
-                embed.footer(|footer| {
-                    footer.text("Classes or locations common to you are underlined.")
-                });
             }
-            return embed;
-        } else {
-            embed
-                .title(member.nick.as_ref().unwrap_or(&user.name))
-                .description(&format!("No data available. This user needs to use `/ccupdate` to enter their courses."))
-                .color(Color::from_rgb(255, 85, 0));
-            return embed;
         }
     }
-    unknown_command(embed, command)
+    lines.sort();
+    lines.dedup();
+    lines
 }
 
-fn get_users_in_location(course_time: &CourseTime) -> Vec<u64> {
-    let mut out = vec![];
-    for (user_id_bytes, course_codes_bytes) in USERDB.iter().filter_map(|d| d.ok()) {
-        let mut buf = [0u8; 8];
-        for i in 0..8 {
-            buf[i] = *(user_id_bytes.get(i).unwrap_or(&0));
-        }
-        let user_id = u64::from_be_bytes(buf);
-        if is_private(user_id) {
-            continue;
-        }
-        let course_codes: Vec<i64> = serde_json::from_slice(&course_codes_bytes).unwrap_or(vec![]);
-        let courses: Vec<&CourseData> = course_codes
-            .iter()
-            .filter_map(|c| COURSEDATA.get(c))
-            .collect();
-        let mut course_times = vec![];
-        for course in courses {
-            for time in &course.times {
-                course_times.push(time);
+// Consecutive meetings on the same day where walking from one building to the
+// next takes longer than the break between them.
+fn walking_warnings(codes: &[i64]) -> Vec<String> {
+    let courses = courses_of(codes);
+    let mut warnings: Vec<(String, Vec<Weekday>)> = vec![];
+    for day in free::WEEKDAYS
+        .iter()
+        .chain([Weekday::Sat, Weekday::Sun].iter())
+    {
+        let meetings = meetings_on(&courses, *day);
+        for pair in meetings.windows(2) {
+            let ((first_course, first), (second_course, second)) = (pair[0], pair[1]);
+            let gap = (second.time.0.time() - first.time.1.time()).num_minutes();
+            let from = first.location.as_deref().and_then(building);
+            let to = second.location.as_deref().and_then(building);
+            let (from, to) = match (from, to) {
+                (Some(from), Some(to)) if from != to && gap >= 0 => (from, to),
+                _ => continue,
+            };
+            let walk = match (buildings::get(&from), buildings::get(&to)) {
+                (Some(a), Some(b)) => buildings::walking_minutes(a, b),
+                _ => continue,
+            };
+            if walk <= gap {
+                continue;
+            }
+            let warning = format!(
+                "`{}` in {} ends {}, `{}` in {} starts {}: about {} min walk, {} min break",
+                first_course.code,
+                from,
+                first.time.1.format("%I:%M %p"),
+                second_course.code,
+                to,
+                second.time.0.format("%I:%M %p"),
+                walk,
+                gap
+            );
+            match warnings.iter_mut().find(|(w, _)| *w == warning) {
+                Some((_, days)) => days.push(*day),
+                None => warnings.push((warning, vec![*day])),
             }
         }
-        if course_times.contains(&course_time) {
-            out.push(user_id);
+    }
+    warnings
+        .into_iter()
+        .map(|(warning, days)| format!("{}: {}", days_label(&days), warning))
+        .collect()
+}
+
+fn parse_clock(s: &str) -> Option<NaiveTime> {
+    let s = s.trim().to_uppercase();
+    ["%H:%M", "%I:%M %p", "%I:%M%p"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(&s, format).ok())
+}
+
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+// The one course search, shared by `/ccsearch` and autocomplete.
+fn search_courses(query: &str) -> impl Iterator<Item = &'static CourseData> {
+    SEARCHINDEX
+        .query(query)
+        .into_iter()
+        .filter_map(|code| COURSEDATA.get(&code))
+}
+
+fn course_choice_name(course: &CourseData) -> String {
+    let mut name = format!(
+        "{} — {}",
+        course.code,
+        course.name.as_deref().unwrap_or("Unknown Name")
+    );
+    let instructor = course.instructor.clone();
+    let first_time = course.times.first().map(|t| {
+        format!(
+            "{} {}",
+            t.day.as_deref().unwrap_or("-"),
+            t.time.0.format("%H:%M")
+        )
+    });
+    match (instructor, first_time) {
+        (Some(instructor), Some(time)) => name.push_str(&format!(" ({}, {})", instructor, time)),
+        (Some(detail), None) | (None, Some(detail)) => name.push_str(&format!(" ({})", detail)),
+        (None, None) => {}
+    }
+    name.chars().take(MAX_CHOICE_LENGTH).collect()
+}
+
+// The value offered for the entry being typed into a `/ccupdate` list, keeping
+// what was already entered. A cut-off list would save the wrong codes, so
+// nothing is offered once it no longer fits.
+fn code_list_choice(entered: &str, code: i64) -> Option<String> {
+    let value = format!("{}{}", entered, code);
+    match value.chars().count() <= MAX_CHOICE_LENGTH {
+        true => Some(value),
+        false => None,
+    }
+}
+
+// What became of each token in a `/ccupdate` list.
+#[derive(Default, PartialEq, Debug)]
+struct ParsedCodes {
+    accepted: Vec<i64>,
+    unknown: Vec<i64>,
+    duplicates: Vec<i64>,
+    // known codes past MAX_CODES
+    dropped: Vec<i64>,
+    invalid: Vec<String>,
+}
+
+fn parse_codes(input: &str, known: impl Fn(i64) -> bool) -> ParsedCodes {
+    let mut seen = HashSet::new();
+    let mut parsed = ParsedCodes::default();
+    for token in input.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        match token.parse::<i64>() {
+            Err(_) => parsed.invalid.push(token.to_string()),
+            Ok(code) if !seen.insert(code) => parsed.duplicates.push(code),
+            Ok(code) if !known(code) => parsed.unknown.push(code),
+            Ok(code) if parsed.accepted.len() >= MAX_CODES => parsed.dropped.push(code),
+            Ok(code) => parsed.accepted.push(code),
         }
     }
-    out
+    parsed
 }
 
-pub fn cclookup<'a>(
+// Whether the caller chose to save sections that meet at the same time.
+fn allows_conflicts(command: &ApplicationCommandInteraction) -> bool {
+    matches!(
+        get_option(command, "allow_conflicts"),
+        Some(ApplicationCommandInteractionDataOptionValue::Boolean(true))
+    )
+}
+
+pub fn ccupdate<'a>(
     embed: &'a mut CreateEmbed,
     command: &ApplicationCommandInteraction,
-) -> &'a mut CreateEmbed {
-    let options = command
-        .data
-        .options
-        .get(0)
-        .expect("Expected code option")
-        .resolved
-        .as_ref()
-        .expect("Expected integer value");
-    if let ApplicationCommandInteractionDataOptionValue::Integer(code) = options {
-        if let Some(course) = COURSEDATA.get(code) {
+) -> BotResult<&'a mut CreateEmbed> {
+    let options = require_option(command, "codes")?;
+    if let ApplicationCommandInteractionDataOptionValue::String(codes) = options {
+        let ParsedCodes {
+            accepted,
+            unknown,
+            duplicates,
+            dropped,
+            invalid,
+        } = parse_codes(codes, |code| COURSEDATA.contains_key(&code));
+        let conflicts = find_conflicts(&accepted);
+        if accepted.is_empty() {
             embed
-                .title(course.code)
-                .description(format!(
-                    "{}",
-                    course
-                        .name
-                        .as_ref()
-                        .unwrap_or(&String::from("Unknown Name"))
-                ))
-                .color(Color::from_rgb(0, 255, 0));
-            for time in &course.times {
-                let users_here: Vec<String> = get_users_in_location(time)
-                    .iter()
-                    .map(|c| format!("<@{}>", c))
-                    .collect();
-                embed.field(
+                .title("No Valid Codes")
+                .description("None of the codes you entered are in the course catalog, so nothing was saved. Use comma-separated unique course codes, like `12345,56789`.")
+                .color(Color::from_rgb(255, 0, 0));
+        } else if !conflicts.is_empty() && !allows_conflicts(command) {
+            embed
+                .title("Time Conflicts")
+                .description("Some of these sections meet at the same time, so nothing was saved. Pick different sections, or run `/ccupdate` again with `allow_conflicts: True` to save them anyway.")
+                .field("Time conflicts", conflicts.join("\n"), false)
+                .color(Color::from_rgb(255, 85, 0));
+        } else {
+            let walks = walking_warnings(&accepted);
+            store_user_codes(*command.user.id.as_u64(), &accepted)?;
+            let warned = !(unknown.is_empty()
+                && duplicates.is_empty()
+                && dropped.is_empty()
+                && invalid.is_empty()
+                && conflicts.is_empty()
+                && walks.is_empty());
+            embed
+                .title(if warned {
+                    "Saved With Warnings"
+                } else {
+                    "Success"
+                })
+                .description("Use the `ccuser` command to see your schedule.")
+                .color(if warned {
+                    Color::from_rgb(255, 85, 0)
+                } else {
+                    Color::from_rgb(0, 255, 0)
+                });
+            let accepted: Vec<String> = accepted
+                .iter()
+                .map(|c| {
                     format!(
-                        "{} | {}-{} | {}",
-                        time.day.as_ref().unwrap_or(&String::from("-")),
-                        time.time.0.format("%I:%M %p"),
-                        time.time.1.format("%I:%M %p"),
-                        time.location.as_ref().unwrap_or(&String::from("-"))
-                    ),
-                    match users_here.len() > 0 {
-                        true => users_here.join(""),
-                        false => "No students found".to_string(),
-                    },
-                    false,
-                );
+                        "{}: {}",
+                        c,
+                        COURSEDATA[c]
+                            .name
+                            .as_ref()
+                            .unwrap_or(&String::from("Unknown Name"))
+                    )
+                })
+                .collect();
+            embed.field("Saved", accepted.join("\n"), false);
+            if !conflicts.is_empty() {
+                embed.field("Time conflicts", conflicts.join("\n"), false);
+            }
+            if !walks.is_empty() {
+                embed.field("Tight walks", walks.join("\n"), false);
+            }
+        }
+        fn quoted<T: std::fmt::Display>(values: &[T]) -> Vec<String> {
+            values.iter().map(|v| format!("`{}`", v)).collect()
+        }
+        for (name, values) in [
+            ("Not in the catalog", quoted(&unknown)),
+            ("Not numbers", quoted(&invalid)),
+            ("Duplicates", quoted(&duplicates)),
+            (
+                "Over the course limit",
+                if dropped.is_empty() {
+                    vec![]
+                } else {
+                    vec![format!(
+                        "{} (max {})",
+                        quoted(&dropped).join(", "),
+                        MAX_CODES
+                    )]
+                },
+            ),
+        ] {
+            if !values.is_empty() {
+                embed.field(name, values.join(", "), false);
             }
-            return embed;
         }
+        return Ok(embed);
     }
-    unknown_command(embed, command)
+    Ok(unknown_command(embed, command))
 }
 
-pub async fn ccfind(command: ApplicationCommandInteraction, ctx: Context) -> serenity::Result<()> {
-    command
-        .create_interaction_response(ctx.http, |response| {
-            response
-                .kind(serenity::model::interactions::InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|message| {
-                    if let Ok(Some(courses_bytes)) = USERDB.get(command.user.id.as_u64().to_be_bytes()) {
-                        let courses: Vec<i64> = serde_json::from_slice(&courses_bytes).unwrap_or(vec![]);
-                        if courses.len() == 0 {
-                            message.create_embed(|embed| {
-                                embed.title("Insufficient Information")
-                                    .description("Make sure you've entered your data into the system. Otherwise this command does not work. Check `/cchelp` for more information.")
-                                    .color(Color::from_rgb(255, 0, 0))
-                            })
-                        }
-                        else {
-                            for code in courses {
-                                message.create_embed(|embed| {
-                                    if let Some(course) = COURSEDATA.get(&code) {
-                                        embed
-                                            .title(course.code)
-                                            .description(format!(
-                                                "{}",
-                                                course
-                                                    .name
-                                                    .as_ref()
-                                                    .unwrap_or(&String::from("Unknown Name"))
-                                            ))
-                                            .color(Color::from_rgb(0, 255, 0));
-                                        for time in &course.times {
-                                            let users_here: Vec<String> = get_users_in_location(time)
-                                                .iter()
-                                                .map(|c| format!("<@{}>", c))
-                                                .collect();
-                                            embed.field(
-                                                format!(
-                                                    "{} | {}-{} | {}",
-                                                    time.day.as_ref().unwrap_or(&String::from("-")),
-                                                    time.time.0.format("%I:%M %p"),
-                                                    time.time.1.format("%I:%M %p"),
-                                                    time.location.as_ref().unwrap_or(&String::from("-"))
-                                                ),
-                                                match users_here.len() > 0 {
-                                                    true => users_here.join(""),
-                                                    false => "No students found".to_string(),
-                                                },
-                                                false,
-                                            );
-                                        }
-                                        embed
-                                        } else {
-                                            embed.title(code)
-                                                .description("This code is not found in the database. Make sure it's a valid unique class code. If it is, then report this to the developer. (check bot's about).")
-                                        }
-                                });
-                            }
-                            message
-                        }
-                    }
-                    else {
-                        message.create_embed(|embed| {
-                                embed.title("Insufficient Information")
-                                    .description("Make sure you've entered your data into the system. Otherwise this command does not work. Check `/cchelp` for more information.")
-                                    .color(Color::from_rgb(255, 0, 0))
-                            })
-                    }
-                })
-        })
-        .await
+#[derive(PartialEq, Debug)]
+enum Add {
+    Done,
+    Duplicate,
+    // the list is already at MAX_CODES
+    Full,
 }
 
-pub fn ccprivacy<'a>(
-    embed: &'a mut CreateEmbed,
-    command: &ApplicationCommandInteraction,
-) -> &'a mut CreateEmbed {
-    let options = command
-        .data
-        .options
-        .get(0)
-        .expect("Expected privacy option")
-        .resolved
-        .as_ref()
-        .expect("Expected boolean value");
-    if let ApplicationCommandInteractionDataOptionValue::Boolean(private) = options {
-        PRIVACYDB
-            .insert(
-                command.user.id.as_u64().to_be_bytes(),
-                private.to_string().as_bytes(),
-            )
-            .unwrap();
-        embed
-            .title("Success")
-            .description(&format!(
-                "Your course data is now {}",
-                if *private { "private" } else { "public" }
-            ))
-            .color(Color::from_rgb(0, 255, 0));
-        return embed;
+fn add_code(codes: &mut Vec<i64>, code: i64) -> Add {
+    if codes.contains(&code) {
+        return Add::Duplicate;
     }
-    unknown_command(embed, command)
+    if codes.len() >= MAX_CODES {
+        return Add::Full;
+    }
+    codes.push(code);
+    Add::Done
 }
 
-pub async fn ccrole<'a>(
-    mut command: ApplicationCommandInteraction,
-    ctx: Context,
-) -> serenity::Result<()> {
-    let mut adding_roles = vec![];
-    let mut removing_roles = vec![];
-    if let Ok(opt_courses_bytes) = USERDB.get(command.user.id.as_u64().to_be_bytes()) {
-        let courses_bytes = opt_courses_bytes
-            .map(|v| v.to_vec())
-            .unwrap_or(b"{}".to_vec());
-        let target_courses: Vec<i64> = serde_json::from_slice(&courses_bytes).unwrap_or(vec![]);
-        let target_courses: Vec<&CourseData> = target_courses
-            .iter()
-            .filter_map(|c| COURSEDATA.get(c))
-            .collect();
-        if let Some(guild_id) = command.guild_id {
-            let mem = command.member.as_mut().unwrap();
-            if let Some(roles) = ROLEMAPPING.servers.get(guild_id.as_u64()) {
-                let intended: HashSet<RoleId> = target_courses
-                    .iter()
-                    .filter_map(|s| roles.get(s.name.as_ref().unwrap()))
-                    .map(|u| RoleId(*u))
-                    .collect();
-                let existing: HashSet<RoleId> = mem
-                    .roles
-                    .iter()
-                    .filter(|r| roles.values().any(|u| r.as_u64() == u))
-                    .cloned()
-                    .collect();
-                let to_remove: Vec<RoleId> = (&existing - &intended).into_iter().collect();
-                let to_add: Vec<RoleId> = (&intended - &existing).into_iter().collect();
-                adding_roles = to_add.clone();
-                removing_roles = to_remove.clone();
-                mem.remove_roles(ctx.http.clone(), &to_remove).await?;
-                mem.add_roles(ctx.http.clone(), &to_add).await.ok();
+fn remove_code(codes: &mut Vec<i64>, code: i64) -> bool {
+    let before = codes.len();
+    codes.retain(|c| *c != code);
+    codes.len() != before
+}
+
+pub fn ccadd<'a>(
+    embed: &'a mut CreateEmbed,
+    command: &ApplicationCommandInteraction,
+) -> BotResult<&'a mut CreateEmbed> {
+    let options = require_option(command, "code")?;
+    if let ApplicationCommandInteractionDataOptionValue::Integer(code) = options {
+        let uid = *command.user.id.as_u64();
+        let mut codes = get_user_codes(uid)?;
+        let course = match COURSEDATA.get(code) {
+            Some(course) => course,
+            None => {
+                embed
+                    .title("Unknown Code")
+                    .description(format!(
+                        "`{}` is not in the course catalog. Make sure it's a valid unique course code.",
+                        code
+                    ))
+                    .color(Color::from_rgb(255, 85, 0));
+                return Ok(embed);
+            }
+        };
+        match add_code(&mut codes, *code) {
+            Add::Done => {}
+            Add::Duplicate => {
+                embed
+                    .title("Already Added")
+                    .description(format!("`{}` is already one of your courses.", code))
+                    .color(Color::from_rgb(255, 85, 0));
+                return Ok(embed);
+            }
+            Add::Full => {
+                embed
+                    .title("Too Many Courses")
+                    .description(format!(
+                        "You can have at most {} courses. Remove one with `/ccremove` first.",
+                        MAX_CODES
+                    ))
+                    .color(Color::from_rgb(255, 85, 0));
+                return Ok(embed);
             }
         }
-    }
-    let adding_roles: Vec<String> = adding_roles.into_iter().map(|r| format!("<@&{}>", *r.as_u64())).collect();
-    let removing_roles: Vec<String> = removing_roles.into_iter().map(|r| format!("<@&{}>", *r.as_u64())).collect();
-    command
-        .create_interaction_response(ctx.http, |response| {
-            response
-            .kind(serenity::model::interactions::InteractionResponseType::ChannelMessageWithSource)
-            .interaction_response_data(|message| {
-                message.create_embed(|embed| {
-                    let mut embed = embed.title(if adding_roles.is_empty() && removing_roles.is_empty() { "No Roles Changed" } else { "Class Roles Changed" });
-                    if !adding_roles.is_empty() {
-                        embed = embed.field("Added", adding_roles.join(" "), false)
-                    }
-                    if !removing_roles.is_empty() {
-                        embed = embed.field("Removed", removing_roles.join(" "), false);
-                    }
-                    embed.color((0, 255, 0))
-                })
+        let conflicts = find_conflicts(&codes);
+        if !conflicts.is_empty() && !allows_conflicts(command) {
+            embed
+                .title("Time Conflicts")
+                .description(format!("`{}` meets at the same time as one of your courses, so it wasn't added. Pick a different section, or run `/ccadd` again with `allow_conflicts: True` to add it anyway.", code))
+                .field("Time conflicts", conflicts.join("\n"), false)
+                .color(Color::from_rgb(255, 85, 0));
+            return Ok(embed);
+        }
+        let walks = walking_warnings(&codes);
+        store_user_codes(uid, &codes)?;
+        let warned = !(conflicts.is_empty() && walks.is_empty());
+        embed
+            .title(if warned {
+                "Saved With Warnings"
+            } else {
+                "Success"
             })
-        })
-        .await
+            .description(format!(
+                "Added **{}: {}**. Use the `ccuser` command to see your schedule.",
+                course.code,
+                course
+                    .name
+                    .as_ref()
+                    .unwrap_or(&String::from("Unknown Name"))
+            ))
+            .color(if warned {
+                Color::from_rgb(255, 85, 0)
+            } else {
+                Color::from_rgb(0, 255, 0)
+            });
+        if !conflicts.is_empty() {
+            embed.field("Time conflicts", conflicts.join("\n"), false);
+        }
+        if !walks.is_empty() {
+            embed.field("Tight walks", walks.join("\n"), false);
+        }
+        return Ok(embed);
+    }
+    Ok(unknown_command(embed, command))
 }
 
-pub fn ccdelete<'a>(
+pub fn ccremove<'a>(
     embed: &'a mut CreateEmbed,
     command: &ApplicationCommandInteraction,
-) -> &'a mut CreateEmbed {
-    if let Ok(Some(_)) = USERDB.remove(&command.user.id.as_u64().to_be_bytes()) {
+) -> BotResult<&'a mut CreateEmbed> {
+    let options = require_option(command, "code")?;
+    if let ApplicationCommandInteractionDataOptionValue::Integer(code) = options {
+        let uid = *command.user.id.as_u64();
+        let mut codes = get_user_codes(uid)?;
+        if !remove_code(&mut codes, *code) {
+            embed
+                .title("Not Found")
+                .description(format!("`{}` is not one of your courses.", code))
+                .color(Color::from_rgb(255, 85, 0));
+            return Ok(embed);
+        }
+        store_user_codes(uid, &codes)?;
         embed
             .title("Success")
-            .description("Your data has been successfully removed.")
+            .description(format!(
+                "Removed `{}`. You now have {} courses.",
+                code,
+                codes.len()
+            ))
             .color(Color::from_rgb(0, 255, 0));
-        return embed;
-    } else {
-        embed
-            .title("Failure")
-            .description("No data was removed since yours wasn't found. It's already deleted or was never there.")
-            .color(Color::from_rgb(255, 85, 0));
-        return embed;
+        return Ok(embed);
     }
+    Ok(unknown_command(embed, command))
 }
 
-pub fn cchelp<'a>(
-    embed: &'a mut CreateEmbed,
-    _command: &ApplicationCommandInteraction,
-) -> &'a mut CreateEmbed {
-    embed
-        .title("Concourse Help Page")
-        .color(Color::from_rgb(0,255,0))
-        .description("Concourse is a bot built for UT that is meant to replace sending pictures of your schedule. It allows you to input your unique course codes and compare them to other students. You can also lookup unique course codes to see who is in the classes. This bot can show if you have lectures with other students, even if unique course codes are different (multiple unique codes usually share lectures).\nBy default your **course data is public to other students**. If you would like to hide it, use the `/ccprivacy`.\nCommands:")
-        .field("`/ccupdate`", "Get started by using this command. Use comma-separated course codes, like this `/ccupdate codes:12349,56789,98765`.", false)
-        .field("`/ccuser`", "If this user has entered their courses already, you can see them and the times/locations, if available for the course. If you've entered your courses already using `/ccupdate` it will underline similarities.", false)
-        .field("`/ccfind`", "Lists all your classes you're attending by their location, and every student in that class.", false)
-        .field("`/cclookup`", "Lookup a certain class code to see if anyone is taking it (async classes won't show people for now). This will list the course's times and if anyone who has entered the codes they will be listed.", false)
+fn events_cover(events: &[ics::Event], time: &CourseTime) -> bool {
+    let days = time.day.as_deref().map(course_days).unwrap_or_default();
+    !days.is_empty()
+        && days.iter().all(|day| {
+            events.iter().any(|event| {
+                let same_time = match (event.start, event.end) {
+                    (Some(start), Some(end)) => {
+                        start.time() == time.time.0.time() && end.time() == time.time.1.time()
+                    }
+                    _ => false,
+                };
+                let same_location = match (&event.location, &time.location) {
+                    (Some(a), Some(b)) => normalize(a) == normalize(b),
+                    _ => true,
+                };
+                same_time && same_location && event.days.contains(day)
+            })
+        })
+}
+
+// Returns the codes that could be matched to a single section, and groups of
+// codes that share every meeting and couldn't be told apart.
+fn match_events(events: &[ics::Event]) -> (Vec<i64>, Vec<Vec<i64>>) {
+    let texts: Vec<&String> = events
+        .iter()
+        .flat_map(|e| vec![e.summary.as_ref(), e.description.as_ref()])
+        .flatten()
+        .collect();
+    let mentioned: HashSet<i64> = texts
+        .iter()
+        .flat_map(|t| t.split(|c: char| !c.is_ascii_digit()))
+        .filter_map(|t| t.parse().ok())
+        .collect();
+    let summaries: Vec<String> = texts.iter().map(|t| normalize(t)).collect();
+    let named = |course: &&CourseData| {
+        let name = normalize(course.name.as_deref().unwrap_or(""));
+        !name.is_empty() && summaries.iter().any(|s| s.contains(&name))
+    };
+
+    let candidates: Vec<&CourseData> = COURSEDATA
+        .values()
+        .filter(|c| !c.times.is_empty() && c.times.iter().all(|t| events_cover(events, t)))
+        .collect();
+    // a section whose meetings are all part of another matched section is
+    // just the shared lecture
+    let mut candidates: Vec<&CourseData> = candidates
+        .iter()
+        .filter(|a| {
+            !candidates.iter().any(|b| {
+                b.times.len() > a.times.len() && a.times.iter().all(|t| b.times.contains(t))
+            })
+        })
+        .cloned()
+        .collect();
+    candidates.sort_by_key(|c| c.code);
+
+    let mut groups: Vec<Vec<&CourseData>> = vec![];
+    for course in candidates {
+        match groups.iter_mut().find(|g| g[0].times == course.times) {
+            Some(group) => group.push(course),
+            None => groups.push(vec![course]),
+        }
+    }
+    let mut codes = vec![];
+    let mut ambiguous = vec![];
+    for mut group in groups {
+        if group.len() > 1 && group.iter().any(|c| mentioned.contains(&c.code)) {
+            group.retain(|c| mentioned.contains(&c.code));
+        }
+        if group.len() > 1 && group.iter().any(named) {
+            group.retain(named);
+        }
+        match group.len() {
+            1 => codes.push(group[0].code),
+            _ => ambiguous.push(group.iter().map(|c| c.code).collect()),
+        }
+    }
+    (codes, ambiguous)
+}
+
+pub async fn ccimport(
+    command: ApplicationCommandInteraction,
+    http: Arc<Http>,
+) -> BotResult<(CreateEmbed, CreateComponents)> {
+    let messages = command
+        .channel_id
+        .messages(&http, |retriever| retriever.limit(50))
+        .await?;
+    // only the caller's latest upload counts, so an old or someone else's
+    // calendar is never picked up by accident
+    let attachment = messages
+        .iter()
+        .find(|m| m.author.id == command.user.id && !m.attachments.is_empty())
+        .and_then(|m| {
+            m.attachments
+                .iter()
+                .find(|a| a.filename.to_lowercase().ends_with(".ics"))
+        });
+    let mut embed = CreateEmbed::default();
+    let mut components = CreateComponents::default();
+    let attachment = match attachment {
+        Some(attachment) => attachment,
+        None => {
+            embed.title("No Calendar Found")
+                .description("Your latest upload in this channel has no `.ics` file. Upload your schedule's `.ics` file in a DM with the bot, then run `/ccimport` there.")
+                .color(Color::from_rgb(255, 0, 0));
+            return Ok((embed, components));
+        }
+    };
+    let contents = String::from_utf8_lossy(&attachment.download().await?).to_string();
+    let filename = &attachment.filename;
+    let (codes, ambiguous) = match_events(&ics::parse(&contents));
+    let dropped = codes.len().saturating_sub(MAX_CODES);
+    let codes = &codes[..(MAX_CODES.min(codes.len()))];
+    let codes_id = codes
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<String>>()
+        .join(",");
+    if codes.is_empty() {
+        embed.title("No Courses Matched")
+            .description(format!("None of the events in `{}` matched a course in the catalog. Use `/ccupdate` to enter your codes instead.", filename))
+            .color(Color::from_rgb(255, 85, 0));
+    } else {
+        embed.title("Confirm Import")
+            .description(format!("These courses were found in `{}`. Confirm to replace your current courses with them.", filename))
+            .color(Color::from_rgb(0, 255, 0));
+    }
+    for code in codes {
+        let course = &COURSEDATA[code];
+        let times: Vec<String> = course.times.iter().map(format_time).collect();
+        embed.field(
+            format!(
+                "**{}: {}**",
+                course.code,
+                course
+                    .name
+                    .as_ref()
+                    .unwrap_or(&String::from("Unknown Name"))
+            ),
+            times.join("\n"),
+            false,
+        );
+    }
+    if !ambiguous.is_empty() {
+        let groups: Vec<String> = ambiguous
+            .iter()
+            .map(|g| {
+                g.iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            })
+            .collect();
+        embed.field(
+            "Couldn't choose between these sections",
+            groups.join("\n"),
+            false,
+        );
+    }
+    if dropped > 0 {
+        embed.footer(|footer| {
+            footer.text(format!(
+                "Only the first {} courses are kept, {} were left out.",
+                MAX_CODES, dropped
+            ))
+        });
+    }
+    if !codes.is_empty() {
+        components.create_action_row(|row| {
+            row.create_button(|button| {
+                button
+                    .style(ButtonStyle::Success)
+                    .label("Confirm")
+                    .custom_id(format!("ccimport:confirm:{}:{}", command.user.id, codes_id))
+            })
+            .create_button(|button| {
+                button
+                    .style(ButtonStyle::Secondary)
+                    .label("Cancel")
+                    .custom_id(format!("ccimport:cancel:{}", command.user.id))
+            })
+        });
+    }
+    Ok((embed, components))
+}
+
+pub async fn ccimport_component(
+    component: MessageComponentInteraction,
+    ctx: Context,
+) -> BotResult<()> {
+    // custom ids look like `ccimport:<action>:<user id>[:<codes>]`
+    let parts: Vec<&str> = component.data.custom_id.split(':').collect();
+    let owner: u64 = parts.get(2).and_then(|s| s.parse().ok()).unwrap_or(0);
+    if owner != *component.user.id.as_u64() {
+        component
+            .create_interaction_response(ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|message| {
+                        message
+                            .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                            .content("Only the user who ran `/ccimport` can use these buttons.")
+                    })
+            })
+            .await?;
+        return Ok(());
+    }
+    let codes: Option<Vec<i64>> = match parts.get(1) {
+        Some(&"confirm") => Some(
+            parts
+                .get(3)
+                .unwrap_or(&"")
+                .split(',')
+                .filter_map(|s| s.parse().ok())
+                .collect(),
+        ),
+        _ => None,
+    };
+    if let Some(codes) = &codes {
+        store_user_codes(owner, codes)?;
+    }
+    component
+        .create_interaction_response(ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|message| {
+                    message
+                        .create_embed(|embed| match &codes {
+                            Some(codes) => embed
+                                .title("Success")
+                                .description(format!(
+                                    "Imported {} courses. Use the `ccuser` command to see your schedule.",
+                                    codes.len()
+                                ))
+                                .color(Color::from_rgb(0, 255, 0)),
+                            None => embed
+                                .title("Import Cancelled")
+                                .description("Your courses were not changed.")
+                                .color(Color::from_rgb(255, 85, 0)),
+                        })
+                        .components(|components| components)
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn ccuser(
+    command: ApplicationCommandInteraction,
+    http: Arc<Http>,
+) -> BotResult<Vec<CreateEmbed>> {
+    if let Some(ApplicationCommandInteractionDataOptionValue::User(user, member)) =
+        get_option(&command, "user")
+    {
+        let mut embed = CreateEmbed::default();
+        // a resolved member means they're in the server the command was run in
+        compare_schedules(
+            &mut embed,
+            &command,
+            user,
+            member.as_ref(),
+            member.is_some(),
+        )?;
+        return Ok(vec![embed]);
+    }
+    // without a user, compare against friends
+    let friends = friends::get(*command.user.id.as_u64())?.accepted;
+    if friends.is_empty() {
+        return Err(BotError::Input(
+            "Pick a user to compare with, or add friends with `/ccfriend add` to compare with them.".to_string(),
+        ));
+    }
+    let http = &http;
+    let users: Vec<serenity::Result<User>> =
+        stream::iter(friends.iter().copied().take(MAX_FRIENDS))
+            .map(|uid| async move { UserId(uid).to_user(http).await })
+            .buffered(MAX_FRIENDS)
+            .collect()
+            .await;
+    let mut embeds = vec![];
+    for user in users {
+        let mut embed = CreateEmbed::default();
+        compare_schedules(&mut embed, &command, &user?, None, false)?;
+        embeds.push(embed);
+    }
+    if friends.len() > MAX_FRIENDS {
+        if let Some(embed) = embeds.last_mut() {
+            embed.footer(|footer| {
+                footer.text(format!(
+                    "Showing {} of your {} friends. Pick a user to compare with anyone else.",
+                    MAX_FRIENDS,
+                    friends.len()
+                ))
+            });
+        }
+    }
+    Ok(embeds)
+}
+
+// The "Compare schedules" and "Show author's schedule" context menu commands.
+pub fn ccuser_target<'a>(
+    embed: &'a mut CreateEmbed,
+    command: &ApplicationCommandInteraction,
+) -> BotResult<&'a mut CreateEmbed> {
+    match target_user(command) {
+        Some((user, member, in_guild)) => compare_schedules(embed, command, user, member, in_guild),
+        None => Ok(unknown_command(embed, command)),
+    }
+}
+
+// The user a context menu command was used on, or the author of the message
+// it was used on, and whether they're in the server it was used in. Discord
+// rarely sends the member for a message, but anyone who posted in the server
+// is in it.
+fn target_user(
+    command: &ApplicationCommandInteraction,
+) -> Option<(&User, Option<&PartialMember>, bool)> {
+    match &command.data.target {
+        Some(ResolvedTarget::User(user, member)) => Some((user, member.as_ref(), member.is_some())),
+        Some(ResolvedTarget::Message(message)) => Some((
+            &message.author,
+            message.member.as_ref(),
+            command.guild_id.is_some(),
+        )),
+        _ => None,
+    }
+}
+
+// There's no member, and so no nickname, in DMs.
+fn display_name<'a>(user: &'a User, member: Option<&'a PartialMember>) -> &'a str {
+    member.and_then(|m| m.nick.as_deref()).unwrap_or(&user.name)
+}
+
+fn compare_schedules<'a>(
+    embed: &'a mut CreateEmbed,
+    command: &ApplicationCommandInteraction,
+    user: &User,
+    member: Option<&PartialMember>,
+    in_guild: bool,
+) -> BotResult<&'a mut CreateEmbed> {
+    let issuer_course_codes = get_user_codes(*command.user.id.as_u64())?;
+    let issuer_courses: Vec<&CourseData> = issuer_course_codes
+        .iter()
+        .filter_map(|c| COURSEDATA.get(c))
+        .collect();
+    let mut issuer_times = vec![];
+    for course in &issuer_courses {
+        for time in &course.times {
+            issuer_times.push(time);
+        }
+    }
+    let name = display_name(user, member);
+    if privacy::visibility(*user.id.as_u64(), &Viewer::of(command), in_guild)? != Visibility::Full {
+        embed
+            .title(name)
+            .description("This user's data is private.")
+            .color(Color::from_rgb(255, 85, 0));
+        return Ok(embed);
+    }
+    if let Some(courses_bytes) = USERDB.get(user.id.as_u64().to_be_bytes())? {
+        let target_codes: Vec<i64> = serde_json::from_slice(&courses_bytes)?;
+        let target_courses: Vec<&CourseData> = target_codes
+            .iter()
+            .filter_map(|c| COURSEDATA.get(c))
+            .collect();
+        embed.title(name).color(Color::from_rgb(0, 255, 0));
+        if command.user.id != user.id {
+            let shared_courses = target_courses
+                .iter()
+                .filter(|c| issuer_course_codes.contains(&c.code))
+                .count();
+            let target_times: Vec<&CourseTime> =
+                target_courses.iter().flat_map(|c| c.times.iter()).collect();
+            let shared_times = target_times
+                .iter()
+                .filter(|t| issuer_times.contains(t))
+                .count();
+            let mut summary = vec![format!(
+                "You share {} {} and {} meeting {} with {}.",
+                shared_courses,
+                if shared_courses == 1 {
+                    "course"
+                } else {
+                    "courses"
+                },
+                shared_times,
+                if shared_times == 1 { "time" } else { "times" },
+                name
+            )];
+            summary.extend(adjacencies(&issuer_times, &target_times, name));
+            embed.description(summary.join("\n"));
+        }
+        for course in target_courses {
+            let course_code_matches = issuer_course_codes.contains(&course.code);
+            let key = match course_code_matches && command.user.id != user.id {
+                true => format!(
+                    "__**{}: {}**__",
+                    course.code,
+                    course
+                        .name
+                        .as_ref()
+                        .unwrap_or(&String::from("Unknown Name"))
+                ),
+                false => format!(
+                    "**{}: {}**",
+                    course.code,
+                    course
+                        .name
+                        .as_ref()
+                        .unwrap_or(&String::from("Unknown Name"))
+                ),
+            };
+            let values: Vec<String> = course
+                .times
+                .iter()
+                .map(|t| {
+                    match issuer_times.contains(&t)
+                        && command.user.id != user.id
+                        && !course_code_matches
+                    {
+                        true => format!(
+                            "__{} | {}-{} | {}__",
+                            t.day.as_ref().unwrap_or(&String::from("-")),
+                            t.time.0.format("%I:%M %p"),
+                            t.time.1.format("%I:%M %p"),
+                            t.location.as_ref().unwrap_or(&String::from("-"))
+                        ),
+                        false => format!(
+                            "{} | {}-{} | {}",
+                            t.day.as_ref().unwrap_or(&String::from("-")),
+                            t.time.0.format("%I:%M %p"),
+                            t.time.1.format("%I:%M %p"),
+                            t.location.as_ref().unwrap_or(&String::from("-"))
+                        ),
+                    }
+                })
+                .collect();
+            let value = values.join("\n");
+            if !values.is_empty() {
+                embed.field(key, value, false);
+            } else {
+                embed.field(key, "No times", false);
+            }
+            embed
+                .footer(|footer| footer.text("Classes or locations common to you are underlined."));
+        }
+        let walks = walking_warnings(&target_codes);
+        if !walks.is_empty() {
+            embed.field("Tight walks", walks.join("\n"), false);
+        }
+        Ok(embed)
+    } else {
+        embed
+            .title(name)
+            .description(
+                "No data available. This user needs to use `/ccupdate` to enter their courses.",
+            )
+            .color(Color::from_rgb(255, 85, 0));
+        Ok(embed)
+    }
+}
+
+// Every user `viewer` may see at least a headcount of, with their course codes.
+fn visible_users(viewer: &Viewer) -> Vec<(u64, Vec<i64>, Visibility)> {
+    let mut out = vec![];
+    for (user_id_bytes, course_codes_bytes) in USERDB.iter().filter_map(|d| d.ok()) {
+        let mut buf = [0u8; 8];
+        for (i, b) in buf.iter_mut().enumerate() {
+            *b = *(user_id_bytes.get(i).unwrap_or(&0));
+        }
+        let user_id = u64::from_be_bytes(buf);
+        // callers drop users outside the server, so everyone left is a member;
+        // a corrupt setting is treated as private rather than failing the lookup
+        let visibility = privacy::visibility(user_id, viewer, true).unwrap_or(Visibility::Hidden);
+        if visibility == Visibility::Hidden {
+            continue;
+        }
+        let course_codes: Vec<i64> = serde_json::from_slice(&course_codes_bytes).unwrap_or(vec![]);
+        out.push((user_id, course_codes, visibility));
+    }
+    out
+}
+
+fn courses_of(codes: &[i64]) -> Vec<&'static CourseData> {
+    codes.iter().filter_map(|c| COURSEDATA.get(c)).collect()
+}
+
+// Meetings of `courses` on `day`, earliest first.
+fn meetings_on<'a>(
+    courses: &[&'a CourseData],
+    day: Weekday,
+) -> Vec<(&'a CourseData, &'a CourseTime)> {
+    let mut meetings: Vec<(&CourseData, &CourseTime)> = courses
+        .iter()
+        .flat_map(|c| c.times.iter().map(move |t| (*c, t)))
+        .filter(|(_, t)| {
+            t.day
+                .as_deref()
+                .map(course_days)
+                .unwrap_or_default()
+                .contains(&day)
+        })
+        .collect();
+    meetings.sort_by_key(|(_, t)| t.time.0.time());
+    meetings
+}
+
+// Meetings of `courses` starting in (`from`, `to`], skipping days without
+// classes.
+fn meetings_between<'a>(
+    courses: &[&'a CourseData],
+    terms: &[campus::Term],
+    from: DateTime<Tz>,
+    to: DateTime<Tz>,
+) -> Vec<(DateTime<Tz>, &'a CourseData, &'a CourseTime)> {
+    let mut out = vec![];
+    let mut date = from.date().naive_local();
+    while date <= to.date().naive_local() {
+        if campus::meets_on(terms, date) {
+            for (course, time) in meetings_on(courses, date.weekday()) {
+                if let Some(start) = from
+                    .timezone()
+                    .from_local_datetime(&date.and_time(time.time.0.time()))
+                    .earliest()
+                {
+                    if from < start && start <= to {
+                        out.push((start, course, time));
+                    }
+                }
+            }
+        }
+        date = date.succ();
+    }
+    out
+}
+
+// Reminders for `uid`'s meetings that should go out in (`from`, `to`], for
+// someone who wants them `minutes` ahead.
+pub fn reminders_due(
+    uid: u64,
+    minutes: i64,
+    from: DateTime<Tz>,
+    to: DateTime<Tz>,
+) -> BotResult<Vec<CreateEmbed>> {
+    let courses = courses_of(&get_user_codes(uid)?);
+    Ok(reminders(&courses, campus::terms(), minutes, from, to)
+        .into_iter()
+        .map(|(start, course, time)| {
+            let mut embed = CreateEmbed::default();
+            embed
+                .title(format!("Class in {} minutes", minutes))
+                .description(format!(
+                    "**{}: {}**\n{} at {}\nStarts <t:{}:R>",
+                    course.code,
+                    course.name.as_deref().unwrap_or("Unknown Name"),
+                    time.location.as_deref().unwrap_or("-"),
+                    start.format("%I:%M %p"),
+                    start.timestamp()
+                ))
+                .color(Color::from_rgb(0, 255, 0))
+                .footer(|footer| footer.text("Use /ccremind to change or turn off reminders."));
+            embed
+        })
+        .collect())
+}
+
+fn reminders<'a>(
+    courses: &[&'a CourseData],
+    terms: &[campus::Term],
+    minutes: i64,
+    from: DateTime<Tz>,
+    to: DateTime<Tz>,
+) -> Vec<(DateTime<Tz>, &'a CourseData, &'a CourseTime)> {
+    let lead = ChronoDuration::minutes(minutes);
+    meetings_between(courses, terms, from + lead, to + lead)
+        .into_iter()
+        // reminders missed while the bot was down are useless once class starts
+        .filter(|(start, _, _)| *start > to)
+        .collect()
+}
+
+// Digests for `uid` whose time of day falls in (`from`, `to`). They go out
+// on weekdays with classes, and only if the user has any that day.
+pub fn digests_due(
+    uid: u64,
+    at: NaiveTime,
+    from: DateTime<Tz>,
+    to: DateTime<Tz>,
+) -> BotResult<Vec<CreateEmbed>> {
+    let courses = courses_of(&get_user_codes(uid)?);
+    let mut out = vec![];
+    for date in digest_dates(&courses, campus::terms(), at, from, to) {
+        let meetings = meetings_on(&courses, date.weekday());
+        let mut lines = vec![];
+        for (i, (course, time)) in meetings.iter().enumerate() {
+            if let Some((_, previous)) = i.checked_sub(1).map(|p| meetings[p]) {
+                let gap = (time.time.0.time() - previous.time.1.time()).num_minutes();
+                lines.push(match gap {
+                    gap if gap <= 0 => "↳ overlaps the class before".to_string(),
+                    gap if gap <= BACK_TO_BACK => "↳ back to back".to_string(),
+                    gap => format!("↳ {} min break", gap),
+                });
+            }
+            lines.push(format!(
+                "**{}-{}** `{}` {} in {}",
+                time.time.0.format("%I:%M %p"),
+                time.time.1.format("%I:%M %p"),
+                course.code,
+                course.name.as_deref().unwrap_or("Unknown Name"),
+                time.location.as_deref().unwrap_or("-")
+            ));
+        }
+        let mut embed = CreateEmbed::default();
+        embed
+            .title(format!("Today's Classes, {}", date.format("%A %B %-d")))
+            .description(lines.join("\n"))
+            .color(Color::from_rgb(0, 255, 0))
+            .footer(|footer| footer.text("Use /ccdigest to change or turn off this digest."));
+        out.push(embed);
+    }
+    Ok(out)
+}
+
+fn digest_dates(
+    courses: &[&CourseData],
+    terms: &[campus::Term],
+    at: NaiveTime,
+    from: DateTime<Tz>,
+    to: DateTime<Tz>,
+) -> Vec<NaiveDate> {
+    let mut out = vec![];
+    let mut date = from.date().naive_local();
+    while date <= to.date().naive_local() {
+        let due = from
+            .timezone()
+            .from_local_datetime(&date.and_time(at))
+            .earliest()
+            .is_some_and(|due| from < due && due <= to);
+        let weekday = !matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+        if due
+            && weekday
+            && campus::meets_on(terms, date)
+            && !meetings_on(courses, date.weekday()).is_empty()
+        {
+            out.push(date);
+        }
+        date = date.succ();
+    }
+    out
+}
+
+// The first meeting of `courses` that starts after `from`, skipping days
+// without classes.
+fn next_meeting<'a>(
+    courses: &[&'a CourseData],
+    terms: &[campus::Term],
+    from: DateTime<Tz>,
+) -> Option<(DateTime<Tz>, &'a CourseData, &'a CourseTime)> {
+    let today = from.date().naive_local();
+    for offset in 0..LOOKAHEAD_DAYS {
+        let date = today + ChronoDuration::days(offset);
+        if !campus::meets_on(terms, date) {
+            continue;
+        }
+        for (course, time) in meetings_on(courses, date.weekday()) {
+            let start = match from
+                .timezone()
+                .from_local_datetime(&date.and_time(time.time.0.time()))
+                .earliest()
+            {
+                Some(start) => start,
+                None => continue,
+            };
+            if start > from {
+                return Some((start, course, time));
+            }
+        }
+    }
+    None
+}
+
+// Users in each of `times` that `viewer` may see, by name or only as a count.
+// Every user is read once, however many meetings a command shows.
+fn attendance<'a>(
+    times: &[&'a CourseTime],
+    viewer: &Viewer,
+) -> HashMap<&'a CourseTime, Vec<(u64, Visibility)>> {
+    let mut out: HashMap<&CourseTime, Vec<(u64, Visibility)>> =
+        times.iter().map(|t| (*t, vec![])).collect();
+    for (uid, codes, visibility) in visible_users(viewer) {
+        let mut seen = HashSet::new();
+        for time in courses_of(&codes).into_iter().flat_map(|c| &c.times) {
+            if let Some(users) = out.get_mut(time) {
+                if seen.insert(time) {
+                    users.push((uid, visibility));
+                }
+            }
+        }
+    }
+    out
+}
+
+// Whether a Discord error means the thing asked for doesn't exist, rather
+// than that the request failed.
+fn is_not_found(why: &serenity::Error) -> bool {
+    match why {
+        serenity::Error::Http(why) => why.status_code() == Some(StatusCode::NOT_FOUND),
+        _ => false,
+    }
+}
+
+// Which of `uids` are in `guild`, looked up a few at a time and remembered for
+// `MEMBERSHIP_EXPIRY`. DMs are scoped by `privacy::visibility` instead, so
+// everyone counts there.
+async fn members_among(
+    http: &Http,
+    guild: Option<GuildId>,
+    uids: HashSet<u64>,
+) -> BotResult<HashSet<u64>> {
+    let guild = match guild {
+        Some(guild) => guild,
+        None => return Ok(uids),
+    };
+    let (mut members, unknown) = cached_members(*guild.as_u64(), uids, Instant::now());
+    let found: Vec<BotResult<(u64, bool)>> = stream::iter(unknown)
+        .map(|uid| async move {
+            match guild.member(http, uid).await {
+                Ok(_) => Ok((uid, true)),
+                Err(why) if is_not_found(&why) => Ok((uid, false)),
+                Err(why) => Err(BotError::Discord(why)),
+            }
+        })
+        .buffer_unordered(MEMBER_LOOKUPS)
+        .collect()
+        .await;
+    let found = found.into_iter().collect::<BotResult<Vec<_>>>()?;
+    cache_members(*guild.as_u64(), &found, Instant::now());
+    members.extend(
+        found
+            .into_iter()
+            .filter(|(_, member)| *member)
+            .map(|(uid, _)| uid),
+    );
+    Ok(members)
+}
+
+// Members of `guild` among `uids` as far as the cache knows, and the users it
+// knows nothing about.
+fn cached_members(guild: u64, uids: HashSet<u64>, now: Instant) -> (HashSet<u64>, Vec<u64>) {
+    let mut cache = MEMBERSHIP.lock().unwrap();
+    cache.retain(|_, (_, expires)| *expires > now);
+    let mut members = HashSet::new();
+    let mut unknown = vec![];
+    for uid in uids {
+        match cache.get(&(guild, uid)) {
+            Some((true, _)) => {
+                members.insert(uid);
+            }
+            Some((false, _)) => {}
+            None => unknown.push(uid),
+        }
+    }
+    (members, unknown)
+}
+
+fn cache_members(guild: u64, found: &[(u64, bool)], now: Instant) {
+    let mut cache = MEMBERSHIP.lock().unwrap();
+    for (uid, member) in found {
+        cache.insert((guild, *uid), (*member, now + MEMBERSHIP_EXPIRY));
+    }
+}
+
+// `attendance` in `times` for the caller of `command`, left with only members
+// of the server it was run in. Reading every user happens on the blocking
+// pool; only the member lookups talk to Discord.
+async fn server_attendance(
+    command: &ApplicationCommandInteraction,
+    http: &Http,
+    times: Vec<&'static CourseTime>,
+) -> BotResult<HashMap<&'static CourseTime, Vec<(u64, Visibility)>>> {
+    let viewer = Viewer::of(command);
+    let mut attendance = deferred::blocking(move || Ok(attendance(&times, &viewer))).await?;
+    let uids = attendance.values().flatten().map(|(uid, _)| *uid).collect();
+    let members = members_among(http, command.guild_id, uids).await?;
+    for users in attendance.values_mut() {
+        users.retain(|(uid, _)| members.contains(uid));
+    }
+    Ok(attendance)
+}
+
+// Users in `course_time`, split into those the viewer may name and how many
+// more they may only count.
+fn attendees_of(
+    course_time: &CourseTime,
+    attendance: &HashMap<&CourseTime, Vec<(u64, Visibility)>>,
+) -> (Vec<u64>, usize) {
+    let (mut named, mut anonymous) = (vec![], 0);
+    for (uid, visibility) in attendance.get(course_time).into_iter().flatten().copied() {
+        match visibility {
+            Visibility::Full => named.push(uid),
+            _ => anonymous += 1,
+        }
+    }
+    (named, anonymous)
+}
+
+// Users who can't be named are still counted, so a class full of private
+// students doesn't look empty.
+fn attendees(named: &[u64], anonymous: usize) -> String {
+    match (named.len(), anonymous) {
+        (_, 0) => mentions(named),
+        (0, n) => format!("+{} private", n),
+        (1, n) => format!("{} (1 student, +{} private)", mentions(named), n),
+        (count, n) => format!("{} ({} students, +{} private)", mentions(named), count, n),
+    }
+}
+
+fn attendees_embed(
+    course: &CourseData,
+    attendance: &HashMap<&CourseTime, Vec<(u64, Visibility)>>,
+) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(course.code)
+        .description(
+            course
+                .name
+                .as_ref()
+                .unwrap_or(&String::from("Unknown Name")),
+        )
+        .color(Color::from_rgb(0, 255, 0));
+    for time in &course.times {
+        let (named, anonymous) = attendees_of(time, attendance);
+        embed.field(
+            format_time(time),
+            match named.is_empty() && anonymous == 0 {
+                true => "No students found".to_string(),
+                false => attendees(&named, anonymous),
+            },
+            false,
+        );
+    }
+    embed
+}
+
+pub async fn cclookup(
+    command: ApplicationCommandInteraction,
+    http: Arc<Http>,
+) -> BotResult<Vec<CreateEmbed>> {
+    let code = match require_option(&command, "code")? {
+        ApplicationCommandInteractionDataOptionValue::Integer(code) => *code,
+        _ => {
+            let mut embed = CreateEmbed::default();
+            unknown_command(&mut embed, &command);
+            return Ok(vec![embed]);
+        }
+    };
+    let course = COURSEDATA
+        .get(&code)
+        .ok_or_else(|| BotError::Input(format!("`{}` isn't in the course catalog.", code)))?;
+    let times = course.times.iter().collect();
+    let attendance = server_attendance(&command, &http, times).await?;
+    Ok(vec![attendees_embed(course, &attendance)])
+}
+
+pub fn ccsearch<'a>(
+    embed: &'a mut CreateEmbed,
+    command: &ApplicationCommandInteraction,
+) -> BotResult<&'a mut CreateEmbed> {
+    let query = match get_option(command, "query") {
+        Some(ApplicationCommandInteractionDataOptionValue::String(query)) => query,
+        _ => return Ok(unknown_command(embed, command)),
+    };
+    let days = match get_option(command, "days") {
+        Some(ApplicationCommandInteractionDataOptionValue::String(days)) => Some(course_days(days)),
+        _ => None,
+    };
+    if matches!(&days, Some(days) if days.is_empty()) {
+        embed
+            .title("Invalid Days")
+            .description(
+                "Days should be written like the course schedule does, e.g. `MWF` or `TTH`.",
+            )
+            .color(Color::from_rgb(255, 0, 0));
+        return Ok(embed);
+    }
+    let mut range = [None, None];
+    for (i, name) in ["after", "before"].iter().enumerate() {
+        if let Some(ApplicationCommandInteractionDataOptionValue::String(time)) =
+            get_option(command, name)
+        {
+            match parse_clock(time) {
+                Some(time) => range[i] = Some(time),
+                None => {
+                    embed
+                        .title("Invalid Time")
+                        .description(format!(
+                            "`{}` isn't a time. Use a format like `13:30` or `1:30 PM`.",
+                            time
+                        ))
+                        .color(Color::from_rgb(255, 0, 0));
+                    return Ok(embed);
+                }
+            }
+        }
+    }
+    let [after, before] = range;
+    let status = match get_option(command, "status") {
+        Some(ApplicationCommandInteractionDataOptionValue::String(status)) => {
+            Some(status.to_lowercase())
+        }
+        _ => None,
+    };
+
+    let results: Vec<&CourseData> = search_courses(query)
+        .filter(|course| {
+            let meets_within = |f: &dyn Fn(&CourseTime) -> bool| {
+                !course.times.is_empty() && course.times.iter().all(f)
+            };
+            if let Some(days) = &days {
+                let on_days = |t: &CourseTime| {
+                    let meeting_days = t.day.as_deref().map(course_days).unwrap_or_default();
+                    !meeting_days.is_empty() && meeting_days.iter().all(|d| days.contains(d))
+                };
+                if !meets_within(&on_days) {
+                    return false;
+                }
+            }
+            if let Some(after) = after {
+                if !meets_within(&|t| t.time.0.time() >= after) {
+                    return false;
+                }
+            }
+            if let Some(before) = before {
+                if !meets_within(&|t| t.time.1.time() <= before) {
+                    return false;
+                }
+            }
+            match (&status, &course.status) {
+                (Some(wanted), Some(status)) => status.to_lowercase().contains(wanted),
+                (Some(_), None) => false,
+                (None, _) => true,
+            }
+        })
+        .collect();
+
+    if results.is_empty() {
+        embed
+            .title(format!("Search: {}", query))
+            .description("No courses matched your search.")
+            .color(Color::from_rgb(255, 85, 0));
+        return Ok(embed);
+    }
+    embed
+        .title(format!("Search: {}", query))
+        .description(format!("{} courses found", results.len()))
+        .color(Color::from_rgb(0, 255, 0));
+    if results.len() > MAX_SEARCH_RESULTS {
+        embed.footer(|footer| {
+            footer.text(format!(
+                "Showing the first {}. Add filters to narrow down your search.",
+                MAX_SEARCH_RESULTS
+            ))
+        });
+    }
+    for course in results.iter().take(MAX_SEARCH_RESULTS) {
+        let mut lines = vec![format!(
+            "{} | {} | {}",
+            course.instructor.as_deref().unwrap_or("-"),
+            course.status.as_deref().unwrap_or("-"),
+            course.instruction_mode.as_deref().unwrap_or("-")
+        )];
+        lines.extend(course.times.iter().map(format_time));
+        embed.field(
+            format!(
+                "**{}: {}**",
+                course.code,
+                course.name.as_deref().unwrap_or("Unknown Name")
+            ),
+            lines.join("\n"),
+            false,
+        );
+    }
+    Ok(embed)
+}
+
+pub async fn ccfind(
+    command: ApplicationCommandInteraction,
+    http: Arc<Http>,
+) -> BotResult<Vec<CreateEmbed>> {
+    let courses = get_user_codes(*command.user.id.as_u64())?;
+    if courses.is_empty() {
+        let mut embed = CreateEmbed::default();
+        embed.title("Insufficient Information")
+            .description("Make sure you've entered your data into the system. Otherwise this command does not work. Check `/cchelp` for more information.")
+            .color(Color::from_rgb(255, 0, 0));
+        return Ok(vec![embed]);
+    }
+    let times = courses_of(&courses)
+        .into_iter()
+        .flat_map(|c| &c.times)
+        .collect();
+    let attendance = server_attendance(&command, &http, times).await?;
+    let mut embeds = vec![];
+    for code in &courses {
+        embeds.push(match COURSEDATA.get(code) {
+            Some(course) => attendees_embed(course, &attendance),
+            None => {
+                let mut embed = CreateEmbed::default();
+                embed.title(code)
+                    .description("This code is not found in the database. Make sure it's a valid unique class code. If it is, then report this to the developer. (check bot's about).");
+                embed
+            }
+        });
+    }
+    Ok(embeds)
+}
+
+fn parse_between(s: &str) -> Option<free::Window> {
+    let (start, end) = s.split_once('-')?;
+    let (start, end) = (parse_clock(start)?, parse_clock(end)?);
+    match start < end {
+        true => Some((start, end)),
+        false => None,
+    }
+}
+
+fn mentions(uids: &[u64]) -> String {
+    uids.iter()
+        .map(|u| format!("<@{}>", u))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// Drops `uid` from every pending `/ccfree` request, along with the requests
+// they started. Returns whether they were part of any.
+pub fn forget_free_requests(uid: u64) -> bool {
+    let mut stored = FREEREQUESTS.lock().unwrap();
+    let before = stored.len();
+    stored.retain(|_, r| r.owner != uid);
+    let mut found = stored.len() != before;
+    for request in stored.values_mut() {
+        if request.users.contains(&uid) {
+            request.users.retain(|u| *u != uid);
+            request.consented.remove(&uid);
+            found = true;
+        }
+    }
+    found
+}
+
+fn free_summary(request: &FreeRequest) -> BotResult<FreeSummary> {
+    let viewer = Viewer {
+        uid: request.owner,
+        guild: request.guild,
+    };
+    let mut busy = vec![];
+    let (mut included, mut excluded, mut missing) = (vec![], vec![], vec![]);
+    for uid in &request.users {
+        // the caller always sees their own schedule
+        if !request.consented.contains(uid)
+            && privacy::visibility(*uid, &viewer, request.members.contains(uid))?
+                != Visibility::Full
+        {
+            excluded.push(*uid);
+            continue;
+        }
+        let codes = get_user_codes(*uid)?;
+        if codes.is_empty() {
+            missing.push(*uid);
+        }
+        included.push(*uid);
+        for time in codes
+            .iter()
+            .filter_map(|c| COURSEDATA.get(c))
+            .flat_map(|c| c.times.iter())
+        {
+            for day in time.day.as_deref().map(course_days).unwrap_or_default() {
+                busy.push((day, time.time.0.time(), time.time.1.time()));
+            }
+        }
+    }
+    let windows: Vec<(Weekday, Vec<free::Window>)> =
+        free::windows(&busy, request.between, request.min_minutes)
+            .into_iter()
+            .map(|(day, windows)| (day, free::longest(windows, FREE_WINDOWS_PER_DAY)))
+            .collect();
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Common Free Time")
+        .description(format!(
+            "Free windows of at least {} minutes between {} and {} for {}",
+            request.min_minutes,
+            request.between.0.format("%I:%M %p"),
+            request.between.1.format("%I:%M %p"),
+            mentions(&included)
+        ))
+        .color(match excluded.is_empty() && missing.is_empty() {
+            true => Color::from_rgb(0, 255, 0),
+            false => Color::from_rgb(255, 85, 0),
+        });
+    for (day, day_windows) in &windows {
+        let lines: Vec<String> = day_windows
+            .iter()
+            .map(|(start, end)| {
+                format!(
+                    "{}-{} ({} min)",
+                    start.format("%I:%M %p"),
+                    end.format("%I:%M %p"),
+                    (*end - *start).num_minutes()
+                )
+            })
+            .collect();
+        embed.field(
+            free::day_name(*day),
+            match lines.is_empty() {
+                true => "No common free time".to_string(),
+                false => lines.join("\n"),
+            },
+            false,
+        );
+    }
+    if !excluded.is_empty() {
+        embed.field(
+            "Left out for privacy",
+            format!(
+                "{}\nThey can press **Include my schedule** to be counted.",
+                mentions(&excluded)
+            ),
+            false,
+        );
+    }
+    if !missing.is_empty() {
+        embed.field(
+            "No courses saved",
+            format!("{}\nThey're counted as free all day.", mentions(&missing)),
+            false,
+        );
+    }
+    Ok(FreeSummary {
+        embed,
+        included: included.len(),
+        excluded,
+        windows,
+    })
+}
+
+// Discord can't show SVGs inline, so the rendering is sent as a file: to the
+// channel for public replies, otherwise to the caller's DMs.
+async fn send_free_render(
+    ctx: &Context,
+    request: &FreeRequest,
+    summary: &FreeSummary,
+) -> BotResult<()> {
+    let image = free::render(
+        &format!("Free time for {} people", summary.included),
+        &summary.windows,
+    );
+    let channel = match request.ephemeral {
+        true => UserId(request.owner).create_dm_channel(&ctx.http).await?.id,
+        false => request.channel,
+    };
+    channel
+        .send_message(&ctx.http, |message| {
+            message.add_file(AttachmentType::Bytes {
+                data: Cow::Owned(image.into_bytes()),
+                filename: "free.svg".to_string(),
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn ccfree(command: ApplicationCommandInteraction, ctx: Context) -> BotResult<()> {
+    let mut users = vec![*command.user.id.as_u64()];
+    let mut members = HashSet::new();
+    for name in ["user", "user2", "user3", "user4", "user5"] {
+        if let Some(ApplicationCommandInteractionDataOptionValue::User(user, member)) =
+            get_option(&command, name)
+        {
+            if !users.contains(user.id.as_u64()) {
+                users.push(*user.id.as_u64());
+            }
+            if member.is_some() {
+                members.insert(*user.id.as_u64());
+            }
+        }
+    }
+    // without any users, look for time with friends
+    if users.len() == 1 {
+        users.extend(
+            friends::get(users[0])?
+                .accepted
+                .into_iter()
+                .take(MAX_FRIENDS),
+        );
+    }
+    if users.len() == 1 {
+        return Err(BotError::Input(
+            "Pick some users, or add friends with `/ccfriend add` to find time with them."
+                .to_string(),
+        ));
+    }
+    let min_minutes = match get_option(&command, "min_minutes") {
+        Some(ApplicationCommandInteractionDataOptionValue::Integer(minutes)) => *minutes,
+        _ => DEFAULT_FREE_MINUTES,
+    };
+    if !(5..=600).contains(&min_minutes) {
+        return Err(BotError::Input(
+            "`min_minutes` has to be between 5 and 600.".to_string(),
+        ));
+    }
+    let between = match get_option(&command, "between") {
+        Some(ApplicationCommandInteractionDataOptionValue::String(between)) => {
+            parse_between(between).ok_or_else(|| {
+                BotError::Input(
+                    "`between` should be a start and end time like `9:00-17:00`.".to_string(),
+                )
+            })?
+        }
+        _ => (NaiveTime::from_hms(8, 0, 0), NaiveTime::from_hms(21, 0, 0)),
+    };
+    let render = matches!(
+        get_option(&command, "render"),
+        Some(ApplicationCommandInteractionDataOptionValue::Boolean(true))
+    );
+    let request = FreeRequest {
+        owner: *command.user.id.as_u64(),
+        users,
+        consented: HashSet::new(),
+        min_minutes,
+        between,
+        render,
+        ephemeral: preferences::is_ephemeral(&command)?,
+        channel: command.channel_id,
+        guild: command.guild_id.map(|g| *g.as_u64()),
+        members,
+        expires: Instant::now() + FREE_REQUEST_EXPIRY,
+    };
+    let summary = free_summary(&request)?;
+    let key = *command.id.as_u64();
+    if !summary.excluded.is_empty() {
+        let mut stored = FREEREQUESTS.lock().unwrap();
+        let now = Instant::now();
+        stored.retain(|_, r| r.expires > now);
+        stored.insert(key, request.clone());
+    }
+    let embed = summary.embed.clone();
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    if request.ephemeral {
+                        message.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+                    }
+                    message
+                        .add_embed(embed)
+                        .components(|c| free_buttons(c, key, &summary.excluded))
+                })
+        })
+        .await?;
+    if request.render {
+        send_free_render(&ctx, &request, &summary).await?;
+    }
+    Ok(())
+}
+
+fn free_buttons<'a>(
+    components: &'a mut CreateComponents,
+    key: u64,
+    excluded: &[u64],
+) -> &'a mut CreateComponents {
+    if excluded.is_empty() {
+        return components;
+    }
+    components.create_action_row(|row| {
+        row.create_button(|button| {
+            button
+                .style(ButtonStyle::Primary)
+                .label("Include my schedule")
+                .custom_id(format!("ccfree:{}", key))
+        })
+    })
+}
+
+pub async fn ccfree_component(
+    component: MessageComponentInteraction,
+    ctx: Context,
+) -> BotResult<()> {
+    // custom ids look like `ccfree:<command interaction id>`
+    let key: u64 = component
+        .data
+        .custom_id
+        .split(':')
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0);
+    let uid = *component.user.id.as_u64();
+    let found = {
+        let mut stored = FREEREQUESTS.lock().unwrap();
+        let now = Instant::now();
+        stored.retain(|_, r| r.expires > now);
+        match stored.get_mut(&key) {
+            Some(request)
+                if uid == request.owner
+                    || !request.users.contains(&uid)
+                    || request.consented.contains(&uid)
+                    || privacy::visibility(
+                        uid,
+                        &Viewer {
+                            uid: request.owner,
+                            guild: request.guild,
+                        },
+                        request.members.contains(&uid),
+                    )
+                    .unwrap_or(Visibility::Hidden)
+                        == Visibility::Full =>
+            {
+                Err("Only users left out for privacy can add their schedule here.")
+            }
+            Some(request) => {
+                request.consented.insert(uid);
+                Ok(request.clone())
+            }
+            None => Err("This request has expired. Run `/ccfree` again."),
+        }
+    };
+    let request = match found {
+        Ok(request) => request,
+        Err(notice) => {
+            component
+                .create_interaction_response(&ctx.http, |response| {
+                    response
+                        .kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|message| {
+                            message
+                                .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                                .content(notice)
+                        })
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+    let summary = free_summary(&request)?;
+    let embed = summary.embed.clone();
+    component
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|message| {
+                    message
+                        .embeds(vec![embed])
+                        .components(|c| free_buttons(c, key, &summary.excluded))
+                })
+        })
+        .await?;
+    if request.render {
+        send_free_render(&ctx, &request, &summary).await?;
+    }
+    Ok(())
+}
+
+pub async fn ccroom(
+    command: ApplicationCommandInteraction,
+    http: Arc<Http>,
+) -> BotResult<Vec<CreateEmbed>> {
+    let query = match require_option(&command, "location")? {
+        ApplicationCommandInteractionDataOptionValue::String(query) => query,
+        _ => return Err(BotError::Input("`location` has to be text.".to_string())),
+    };
+    let (building, room) = location_key(query).ok_or_else(|| {
+        BotError::Input(
+            "`location` should be a building or room, like `GDC` or `GDC 2.216`.".to_string(),
+        )
+    })?;
+    let mut meetings: Vec<(&CourseData, &CourseTime)> = COURSEDATA
+        .values()
+        .flat_map(|c| c.times.iter().map(move |t| (c, t)))
+        .filter(
+            |(_, t)| match t.location.as_deref().and_then(location_key) {
+                Some((b, r)) => b == building && (room.is_empty() || r == room),
+                None => false,
+            },
+        )
+        .collect();
+    meetings.sort_by_key(|(c, t)| (t.time.0.time(), t.location.clone(), c.code));
+    let times = meetings.iter().map(|(_, t)| *t).collect();
+    let attendance = server_attendance(&command, &http, times).await?;
+
+    let mut embed = CreateEmbed::default();
+    embed.title(match room.is_empty() {
+        true => building.clone(),
+        false => format!(
+            "{} {}",
+            building,
+            query.trim_start_matches(|c: char| !c.is_ascii_digit())
+        ),
+    });
+    if meetings.is_empty() {
+        embed
+            .description("No meetings in the catalog are held here. Check the building code and room number.")
+            .color(Color::from_rgb(255, 85, 0));
+        return Ok(vec![embed]);
+    }
+    embed
+        .description(format!(
+            "{} meetings, with the students in them",
+            meetings.len()
+        ))
+        .color(Color::from_rgb(0, 255, 0));
+    for day in free::WEEKDAYS
+        .iter()
+        .chain([Weekday::Sat, Weekday::Sun].iter())
+    {
+        let mut lines = vec![];
+        for (course, time) in meetings.iter().filter(|(_, t)| {
+            t.day
+                .as_deref()
+                .map(course_days)
+                .unwrap_or_default()
+                .contains(day)
+        }) {
+            let (named, anonymous) = attendees_of(time, &attendance);
+            lines.push(format!(
+                "**{}-{}** `{}` {}{} {}",
+                time.time.0.format("%I:%M %p"),
+                time.time.1.format("%I:%M %p"),
+                course.code,
+                course.name.as_deref().unwrap_or("Unknown Name"),
+                match room.is_empty() {
+                    true => format!(" in {}", time.location.as_deref().unwrap_or("-")),
+                    false => String::new(),
+                },
+                attendees(&named, anonymous)
+            ));
+        }
+        if !lines.is_empty() {
+            embed.field(free::day_name(*day), lines.join("\n"), false);
+        }
+    }
+    Ok(vec![embed])
+}
+
+pub fn ccprivacy<'a>(
+    embed: &'a mut CreateEmbed,
+    command: &ApplicationCommandInteraction,
+) -> BotResult<&'a mut CreateEmbed> {
+    let uid = *command.user.id.as_u64();
+    // without any options, show the current settings
+    let mut changed = false;
+    if let Some(ApplicationCommandInteractionDataOptionValue::String(name)) =
+        get_option(command, "level")
+    {
+        let level = Level::parse(name)
+            .ok_or_else(|| BotError::Input(format!("`{}` isn't a privacy level.", name)))?;
+        privacy::set_level(uid, level)?;
+        changed = true;
+    }
+    if let Some(ApplicationCommandInteractionDataOptionValue::Boolean(shown)) =
+        get_option(command, "server")
+    {
+        let guild_id = command.guild_id.ok_or_else(|| {
+            BotError::Input("`server` only works when run in a server.".to_string())
+        })?;
+        privacy::set_shown_in(uid, *guild_id.as_u64(), *shown)?;
+        changed = true;
+    }
+    let level = privacy::level(uid)?;
+    let mut lines = vec![format!(
+        "Your course data is **{}**: {}.",
+        level.name(),
+        level.description()
+    )];
+    match privacy::hidden_guilds(uid)? {
+        0 => {}
+        n => lines.push(format!(
+            "You're hidden from everyone in {} server{}. Use `/ccprivacy server:True` in one to show yourself there again.",
+            n,
+            if n == 1 { "" } else { "s" }
+        )),
+    }
+    Ok(embed
+        .title(if changed { "Success" } else { "Privacy" })
+        .description(lines.join("\n"))
+        .color(Color::from_rgb(0, 255, 0)))
+}
+
+pub fn ccreplies<'a>(
+    embed: &'a mut CreateEmbed,
+    command: &ApplicationCommandInteraction,
+) -> BotResult<&'a mut CreateEmbed> {
+    let hidden = match get_option(command, "hidden") {
+        Some(ApplicationCommandInteractionDataOptionValue::Boolean(hidden)) => *hidden,
+        _ => return Ok(unknown_command(embed, command)),
+    };
+    let target = match get_option(command, "command") {
+        Some(ApplicationCommandInteractionDataOptionValue::String(name)) => Some(name.as_str()),
+        _ => None,
+    };
+    preferences::set_hidden(*command.user.id.as_u64(), target, hidden)?;
+    Ok(embed
+        .title("Success")
+        .description(format!(
+            "Replies to {} will now be {}.",
+            match target {
+                Some(name) => format!("`/{}`", name),
+                None => "all your commands".to_string(),
+            },
+            if hidden {
+                "visible only to you"
+            } else {
+                "posted in the channel"
+            }
+        ))
+        .color(Color::from_rgb(0, 255, 0)))
+}
+
+pub async fn ccrole(
+    mut command: ApplicationCommandInteraction,
+    http: Arc<Http>,
+) -> BotResult<Vec<CreateEmbed>> {
+    let guild_id = command.guild_id.ok_or_else(|| {
+        BotError::Input(
+            "`/ccrole` gives out this server's class roles, so it only works in a server."
+                .to_string(),
+        )
+    })?;
+    let mem = command
+        .member
+        .as_mut()
+        .ok_or_else(|| BotError::Internal("server command without member data".to_string()))?;
+    let target_courses = get_user_codes(*command.user.id.as_u64())?;
+    let target_courses: Vec<&CourseData> = target_courses
+        .iter()
+        .filter_map(|c| COURSEDATA.get(c))
+        .collect();
+    let mut adding_roles = vec![];
+    let mut removing_roles = vec![];
+    let mapping = ROLEMAPPING
+        .as_ref()
+        .map_err(|why| BotError::Catalog(why.clone()))?;
+    if let Some(roles) = mapping.servers.get(guild_id.as_u64()) {
+        let intended: HashSet<RoleId> = target_courses
+            .iter()
+            .filter_map(|s| s.name.as_ref().and_then(|name| roles.get(name)))
+            .map(|u| RoleId(*u))
+            .collect();
+        let existing: HashSet<RoleId> = mem
+            .roles
+            .iter()
+            .filter(|r| roles.values().any(|u| r.as_u64() == u))
+            .cloned()
+            .collect();
+        let to_remove: Vec<RoleId> = (&existing - &intended).into_iter().collect();
+        let to_add: Vec<RoleId> = (&intended - &existing).into_iter().collect();
+        adding_roles = to_add.clone();
+        removing_roles = to_remove.clone();
+        mem.remove_roles(&http, &to_remove).await?;
+        mem.add_roles(&http, &to_add).await?;
+    }
+    let adding_roles: Vec<String> = adding_roles
+        .into_iter()
+        .map(|r| format!("<@&{}>", *r.as_u64()))
+        .collect();
+    let removing_roles: Vec<String> = removing_roles
+        .into_iter()
+        .map(|r| format!("<@&{}>", *r.as_u64()))
+        .collect();
+    let mut embed = CreateEmbed::default();
+    embed.title(if adding_roles.is_empty() && removing_roles.is_empty() {
+        "No Roles Changed"
+    } else {
+        "Class Roles Changed"
+    });
+    if !adding_roles.is_empty() {
+        embed.field("Added", adding_roles.join(" "), false);
+    }
+    if !removing_roles.is_empty() {
+        embed.field("Removed", removing_roles.join(" "), false);
+    }
+    embed.color((0, 255, 0));
+    Ok(vec![embed])
+}
+
+pub async fn ccnow(
+    command: ApplicationCommandInteraction,
+    http: Arc<Http>,
+) -> BotResult<Vec<CreateEmbed>> {
+    let guild_id = command.guild_id.ok_or_else(|| {
+        BotError::Input(
+            "`/ccnow` shows who's in class in a server, so it only works in one.".to_string(),
+        )
+    })?;
+    let now = campus::now();
+    let (today, time) = (now.date().naive_local(), now.time());
+    let mut embed = CreateEmbed::default();
+    let heading = match campus::current_term(today) {
+        Some(term) => format!("{} ({})", now.format("%A %I:%M %p"), term.name),
+        None => now.format("%A %I:%M %p").to_string(),
+    };
+    embed.title("Right Now");
+    if !campus::has_classes(today) {
+        embed
+            .description(format!("{}\nNo classes meet today.", heading))
+            .color(Color::from_rgb(255, 85, 0));
+        return Ok(vec![embed]);
+    }
+
+    // the caller shows up even if their own data is private
+    let caller = *command.user.id.as_u64();
+    let viewer = Viewer::of(&command);
+    let users = deferred::blocking(move || {
+        let mut users: Vec<(u64, Vec<i64>)> = visible_users(&viewer)
+            .into_iter()
+            .filter(|(_, _, visibility)| *visibility == Visibility::Full)
+            .map(|(uid, codes, _)| (uid, codes))
+            .collect();
+        if !users.iter().any(|(uid, _)| *uid == caller) {
+            users.push((caller, get_user_codes(caller)?));
+        }
+        Ok(users)
+    })
+    .await?;
+    let mut listed = vec![];
+    for (uid, codes) in users {
+        let meetings = meetings_on(&courses_of(&codes), today.weekday());
+        let current = meetings
+            .iter()
+            .find(|(_, t)| t.time.0.time() <= time && time < t.time.1.time())
+            .copied();
+        let next = meetings
+            .iter()
+            .find(|(_, t)| t.time.0.time() > time)
+            .copied();
+        if current.is_some() || next.is_some() {
+            listed.push((uid, current, next));
+        }
+    }
+    // only members of this server are listed, and only users with classes
+    // left today need looking up
+    let others = listed
+        .iter()
+        .map(|(uid, _, _)| *uid)
+        .filter(|uid| *uid != caller)
+        .collect();
+    let members = members_among(&http, Some(guild_id), others).await?;
+    let mut in_class = vec![];
+    let mut free = vec![];
+    for (uid, current, next) in listed {
+        if uid != caller && !members.contains(&uid) {
+            continue;
+        }
+        match (current, next) {
+            (Some((course, t)), _) => in_class.push((
+                t.time.1.time(),
+                format!(
+                    "<@{}> `{}` {} in {} until {}",
+                    uid,
+                    course.code,
+                    course.name.as_deref().unwrap_or("Unknown Name"),
+                    t.location.as_deref().unwrap_or("-"),
+                    t.time.1.format("%I:%M %p")
+                ),
+            )),
+            (None, Some((_, t))) => free.push((
+                t.time.0.time(),
+                format!(
+                    "<@{}> free until {} (next in {})",
+                    uid,
+                    t.time.0.format("%I:%M %p"),
+                    t.location.as_deref().unwrap_or("-")
+                ),
+            )),
+            (None, None) => {}
+        }
+    }
+    in_class.sort();
+    free.sort();
+    embed
+        .description(heading)
+        .color(Color::from_rgb(0, 255, 0))
+        .footer(|footer| footer.text("Only users with classes left today are listed."));
+    for (name, lines) in [("In class", in_class), ("Free", free)] {
+        let lines: Vec<String> = lines.into_iter().map(|(_, line)| line).collect();
+        embed.field(
+            name,
+            match lines.is_empty() {
+                true => "Nobody".to_string(),
+                false => lines.join("\n"),
+            },
+            false,
+        );
+    }
+    Ok(vec![embed])
+}
+
+pub fn ccnext<'a>(
+    embed: &'a mut CreateEmbed,
+    command: &ApplicationCommandInteraction,
+) -> BotResult<&'a mut CreateEmbed> {
+    // defaults to the caller
+    let (user, nick, in_guild) = match get_option(command, "user") {
+        Some(ApplicationCommandInteractionDataOptionValue::User(user, member)) => (
+            user,
+            member.as_ref().and_then(|m| m.nick.clone()),
+            member.is_some(),
+        ),
+        _ => (
+            &command.user,
+            command.member.as_ref().and_then(|m| m.nick.clone()),
+            true,
+        ),
+    };
+    let name = nick.unwrap_or_else(|| user.name.clone());
+    if privacy::visibility(*user.id.as_u64(), &Viewer::of(command), in_guild)? != Visibility::Full {
+        return Ok(embed
+            .title(name)
+            .description("This user's data is private.")
+            .color(Color::from_rgb(255, 85, 0)));
+    }
+    let codes = get_user_codes(*user.id.as_u64())?;
+    if codes.is_empty() {
+        return Ok(embed
+            .title(name)
+            .description(
+                "No data available. This user needs to use `/ccupdate` to enter their courses.",
+            )
+            .color(Color::from_rgb(255, 85, 0)));
+    }
+    match next_meeting(&courses_of(&codes), campus::terms(), campus::now()) {
+        Some((start, course, time)) => Ok(embed
+            .title(format!("Next Class for {}", name))
+            .description(format!(
+                "**{}: {}**\n{} in {}\nStarts <t:{}:R>",
+                course.code,
+                course.name.as_deref().unwrap_or("Unknown Name"),
+                start.format("%A, %B %-d at %I:%M %p"),
+                time.location.as_deref().unwrap_or("-"),
+                start.timestamp()
+            ))
+            .color(Color::from_rgb(0, 255, 0))),
+        None => Ok(embed
+            .title(format!("Next Class for {}", name))
+            .description(format!(
+                "No meetings in the next {} days. The term may be over.",
+                LOOKAHEAD_DAYS
+            ))
+            .color(Color::from_rgb(255, 85, 0))),
+    }
+}
+
+pub fn ccremind<'a>(
+    embed: &'a mut CreateEmbed,
+    command: &ApplicationCommandInteraction,
+) -> BotResult<&'a mut CreateEmbed> {
+    let enabled = match require_option(command, "enabled")? {
+        ApplicationCommandInteractionDataOptionValue::Boolean(enabled) => *enabled,
+        _ => return Ok(unknown_command(embed, command)),
+    };
+    let minutes = match get_option(command, "minutes") {
+        Some(ApplicationCommandInteractionDataOptionValue::Integer(minutes)) => *minutes,
+        _ => DEFAULT_REMINDER_MINUTES,
+    };
+    if !(1..=120).contains(&minutes) {
+        return Err(BotError::Input(
+            "`minutes` has to be between 1 and 120.".to_string(),
+        ));
+    }
+    let uid = *command.user.id.as_u64();
+    let mut schedule = scheduler::get(uid)?;
+    schedule.reminder_minutes = match enabled {
+        true => Some(minutes),
+        false => None,
+    };
+    scheduler::set(uid, &schedule)?;
+    Ok(embed
+        .title("Success")
+        .description(match enabled {
+            true => format!("You'll get a DM {} minutes before each of your classes. Make sure you allow DMs from server members.", minutes),
+            false => "You won't get class reminders anymore.".to_string(),
+        })
+        .color(Color::from_rgb(0, 255, 0)))
+}
+
+pub fn ccdigest<'a>(
+    embed: &'a mut CreateEmbed,
+    command: &ApplicationCommandInteraction,
+) -> BotResult<&'a mut CreateEmbed> {
+    let enabled = match require_option(command, "enabled")? {
+        ApplicationCommandInteractionDataOptionValue::Boolean(enabled) => *enabled,
+        _ => return Ok(unknown_command(embed, command)),
+    };
+    let at = match get_option(command, "time") {
+        Some(ApplicationCommandInteractionDataOptionValue::String(time)) => parse_clock(time)
+            .ok_or_else(|| {
+                BotError::Input("`time` should be a time of day like `7:30`.".to_string())
+            })?,
+        _ => NaiveTime::from_hms(7, 30, 0),
+    };
+    let uid = *command.user.id.as_u64();
+    let mut schedule = scheduler::get(uid)?;
+    schedule.digest = match enabled {
+        true => Some(at),
+        false => None,
+    };
+    scheduler::set(uid, &schedule)?;
+    Ok(embed
+        .title("Success")
+        .description(match enabled {
+            true => format!("You'll get a DM with the day's classes at {} on weekdays you have class. Make sure you allow DMs from server members.", at.format("%I:%M %p")),
+            false => "You won't get a daily digest anymore.".to_string(),
+        })
+        .color(Color::from_rgb(0, 255, 0)))
+}
+
+// DMs `to` a friend request from `from` with buttons to answer it. Returns
+// false if their DMs are closed.
+async fn send_friend_request(http: &Http, from: u64, to: u64) -> bool {
+    let channel = match UserId(to).create_dm_channel(http).await {
+        Ok(channel) => channel,
+        Err(_) => return false,
+    };
+    channel
+        .send_message(http, |message| {
+            message
+                .embed(|embed| {
+                    embed
+                        .title("Friend Request")
+                        .description(format!(
+                            "<@{}> wants to be friends. Friends can see each other's schedules unless they're set to `private`.",
+                            from
+                        ))
+                        .color(Color::from_rgb(0, 255, 0))
+                })
+                .components(|components| {
+                    components.create_action_row(|row| {
+                        row.create_button(|button| {
+                            button
+                                .style(ButtonStyle::Success)
+                                .label("Accept")
+                                .custom_id(format!("ccfriend:accept:{}", from))
+                        })
+                        .create_button(|button| {
+                            button
+                                .style(ButtonStyle::Danger)
+                                .label("Decline")
+                                .custom_id(format!("ccfriend:decline:{}", from))
+                        })
+                    })
+                })
+        })
+        .await
+        .is_ok()
+}
+
+async fn send_friend_accepted(http: &Http, to: u64, by: u64) {
+    if let Ok(channel) = UserId(to).create_dm_channel(http).await {
+        let sent = channel
+            .send_message(http, |message| {
+                message.embed(|embed| {
+                    embed
+                        .title("Friend Request Accepted")
+                        .description(format!("You're now friends with <@{}>.", by))
+                        .color(Color::from_rgb(0, 255, 0))
+                })
+            })
+            .await;
+        if let Err(why) = sent {
+            println!(
+                "Cannot tell {} about an accepted friend request: {}",
+                to, why
+            );
+        }
+    }
+}
+
+pub async fn ccfriend(
+    command: ApplicationCommandInteraction,
+    http: Arc<Http>,
+) -> BotResult<Vec<CreateEmbed>> {
+    let uid = *command.user.id.as_u64();
+    let subcommand = command
+        .data
+        .options
+        .first()
+        .ok_or_else(|| BotError::Input("Use `add`, `remove` or `list`.".to_string()))?;
+    let target = subcommand
+        .options
+        .iter()
+        .find(|o| o.name == "user")
+        .and_then(|o| match &o.resolved {
+            Some(ApplicationCommandInteractionDataOptionValue::User(user, _)) => Some(user),
+            _ => None,
+        });
+    let mut embed = CreateEmbed::default();
+    match (subcommand.name.as_str(), target) {
+        ("list", _) => {
+            let friends = friends::get(uid)?;
+            embed.title("Friends").color(Color::from_rgb(0, 255, 0));
+            for (name, uids) in [
+                ("Friends", &friends.accepted),
+                ("Asking you", &friends.incoming),
+                ("Waiting on", &friends.outgoing),
+            ] {
+                let uids: Vec<u64> = uids.iter().copied().collect();
+                embed.field(
+                    name,
+                    match uids.is_empty() {
+                        true => "Nobody".to_string(),
+                        false => mentions(&uids),
+                    },
+                    false,
+                );
+            }
+            embed.footer(|footer| footer.text("Accept a request with `/ccfriend add`."));
+        }
+        ("add", Some(user)) if user.id == command.user.id || user.bot => {
+            return Err(BotError::Input(
+                "You can't add yourself or a bot as a friend.".to_string(),
+            ));
+        }
+        ("add", Some(user)) => {
+            let other = *user.id.as_u64();
+            match friends::request(uid, other)? {
+                Request::Sent => {
+                    embed
+                        .title("Request Sent")
+                        .description(match send_friend_request(&http, uid, other).await {
+                            true => format!("<@{}> can accept from the DM the bot sent them, or with `/ccfriend add`.", other),
+                            false => format!("<@{}> doesn't accept DMs from the bot, so let them know to accept with `/ccfriend add`.", other),
+                        })
+                        .color(Color::from_rgb(0, 255, 0));
+                }
+                Request::Accepted => {
+                    send_friend_accepted(&http, other, uid).await;
+                    embed
+                        .title("Success")
+                        .description(format!("You're now friends with <@{}>.", other))
+                        .color(Color::from_rgb(0, 255, 0));
+                }
+                Request::AlreadySent => {
+                    embed
+                        .title("Request Pending")
+                        .description(format!(
+                            "You've already asked <@{}>. They can accept with `/ccfriend add`.",
+                            other
+                        ))
+                        .color(Color::from_rgb(255, 85, 0));
+                }
+                Request::AlreadyFriends => {
+                    embed
+                        .title("Already Friends")
+                        .description(format!("You're already friends with <@{}>.", other))
+                        .color(Color::from_rgb(255, 85, 0));
+                }
+            }
+        }
+        ("remove", Some(user)) => {
+            let other = *user.id.as_u64();
+            match friends::remove(uid, other)? {
+                true => embed
+                    .title("Success")
+                    .description(format!(
+                        "<@{}> is no longer your friend, and any requests between you were cancelled.",
+                        other
+                    ))
+                    .color(Color::from_rgb(0, 255, 0)),
+                false => embed
+                    .title("Not Friends")
+                    .description(format!(
+                        "You aren't friends with <@{}> and there are no requests between you.",
+                        other
+                    ))
+                    .color(Color::from_rgb(255, 85, 0)),
+            };
+        }
+        _ => {
+            unknown_command(&mut embed, &command);
+        }
+    }
+    Ok(vec![embed])
+}
+
+pub async fn ccfriend_component(
+    component: MessageComponentInteraction,
+    ctx: Context,
+) -> BotResult<()> {
+    // custom ids look like `ccfriend:<accept|decline>:<requester>`
+    let mut parts = component.data.custom_id.split(':').skip(1);
+    let action = parts.next().unwrap_or("");
+    let from: u64 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+    let uid = *component.user.id.as_u64();
+    let (accepted, title, description) = match action {
+        "accept" if friends::accept(uid, from)? => (
+            true,
+            "Success",
+            format!("You're now friends with <@{}>.", from),
+        ),
+        "decline" if friends::get(uid)?.incoming.contains(&from) => {
+            friends::remove(uid, from)?;
+            (
+                false,
+                "Request Declined",
+                format!("You declined <@{}>'s friend request.", from),
+            )
+        }
+        _ => (
+            false,
+            "Request Closed",
+            "This request was cancelled or already answered.".to_string(),
+        ),
+    };
+    component
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|message| {
+                    message
+                        .create_embed(|embed| {
+                            embed
+                                .title(title)
+                                .description(description)
+                                .color(match accepted {
+                                    true => Color::from_rgb(0, 255, 0),
+                                    false => Color::from_rgb(255, 85, 0),
+                                })
+                        })
+                        .components(|components| components)
+                })
+        })
+        .await?;
+    if accepted {
+        send_friend_accepted(&ctx.http, from, uid).await;
+    }
+    Ok(())
+}
+
+pub async fn ccmydata(
+    command: ApplicationCommandInteraction,
+    http: Arc<Http>,
+) -> BotResult<Vec<CreateEmbed>> {
+    let uid = *command.user.id.as_u64();
+    let export = serde_json::to_vec_pretty(&data::export(uid)?)?;
+    // the export is personal, so it always goes to DMs
+    let channel = command.user.create_dm_channel(&http).await?;
+    channel
+        .send_message(&http, |message| {
+            message
+                .content("Here's everything Concourse stores about you.")
+                .add_file(AttachmentType::Bytes {
+                    data: Cow::Owned(export),
+                    filename: "concourse-data.json".to_string(),
+                })
+        })
+        .await
+        .map_err(|_| {
+            BotError::Input(
+                "Couldn't DM you your data. Allow DMs from the bot and try again.".to_string(),
+            )
+        })?;
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Success")
+        .description("Your data was sent to your DMs as a JSON file.")
+        .color(Color::from_rgb(0, 255, 0));
+    Ok(vec![embed])
+}
+
+pub async fn ccdelete(command: ApplicationCommandInteraction, ctx: Context) -> BotResult<()> {
+    let ephemeral = preferences::is_ephemeral(&command)?;
+    command
+        .create_interaction_response(ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    if ephemeral {
+                        message.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+                    }
+                    message
+                        .create_embed(|embed| {
+                            embed
+                                .title("Confirm Deletion")
+                                .description("This removes everything the bot stores about you: your courses, privacy settings, reply preferences, reminders, friends and pending requests. It can't be undone. Use `/ccmydata` first if you want a copy.")
+                                .color(Color::from_rgb(255, 85, 0))
+                        })
+                        .components(|components| {
+                            components.create_action_row(|row| {
+                                row.create_button(|button| {
+                                    button
+                                        .style(ButtonStyle::Danger)
+                                        .label("Delete everything")
+                                        .custom_id(format!("ccdelete:confirm:{}", command.user.id))
+                                })
+                                .create_button(|button| {
+                                    button
+                                        .style(ButtonStyle::Secondary)
+                                        .label("Cancel")
+                                        .custom_id(format!("ccdelete:cancel:{}", command.user.id))
+                                })
+                            })
+                        })
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn ccdelete_component(
+    component: MessageComponentInteraction,
+    ctx: Context,
+) -> BotResult<()> {
+    // custom ids look like `ccdelete:<action>:<user id>`
+    let parts: Vec<&str> = component.data.custom_id.split(':').collect();
+    let owner: u64 = parts.get(2).and_then(|s| s.parse().ok()).unwrap_or(0);
+    if owner != *component.user.id.as_u64() {
+        component
+            .create_interaction_response(ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|message| {
+                        message
+                            .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                            .content("Only the user who ran `/ccdelete` can use these buttons.")
+                    })
+            })
+            .await?;
+        return Ok(());
+    }
+    let removed = match parts.get(1) {
+        Some(&"confirm") => Some(data::erase(owner)?),
+        _ => None,
+    };
+    component
+        .create_interaction_response(ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|message| {
+                    message
+                        .create_embed(|embed| match &removed {
+                            Some(removed) if !removed.is_empty() => embed
+                                .title("Success")
+                                .description(format!(
+                                    "Your data has been removed from: {}.",
+                                    removed.join(", ")
+                                ))
+                                .color(Color::from_rgb(0, 255, 0)),
+                            Some(_) => embed
+                                .title("Nothing to Delete")
+                                .description("The bot wasn't storing anything about you.")
+                                .color(Color::from_rgb(255, 85, 0)),
+                            None => embed
+                                .title("Deletion Cancelled")
+                                .description("Your data was not changed.")
+                                .color(Color::from_rgb(255, 85, 0)),
+                        })
+                        .components(|components| components)
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+pub fn cchelp<'a>(
+    embed: &'a mut CreateEmbed,
+    _command: &ApplicationCommandInteraction,
+) -> &'a mut CreateEmbed {
+    embed
+        .title("Concourse Help Page")
+        .color(Color::from_rgb(0,255,0))
+        .description("Concourse is a bot built for UT that is meant to replace sending pictures of your schedule. It allows you to input your unique course codes and compare them to other students. You can also lookup unique course codes to see who is in the classes. This bot can show if you have lectures with other students, even if unique course codes are different (multiple unique codes usually share lectures).\nBy default your **course data is public to other students**. If you would like to hide it, use `/ccprivacy` to choose who can see it.\nEvery command except `/ccrole` and `/ccnow` also works in a DM with the bot, if you'd rather manage your data privately.\nCommands:")
+        .field("`/ccupdate`", "Get started by using this command. Use comma-separated course codes, like this `/ccupdate codes:12349,56789,98765`. Any codes that couldn't be saved are listed in the response, and sections that meet at the same time aren't saved unless you set `allow_conflicts`.", false)
+        .field("`/ccadd`", "Add a single course code to the ones you've already entered. It's checked the same way as `/ccupdate`, so a section that meets at the same time as one of yours needs `allow_conflicts`.", false)
+        .field("`/ccremove`", "Remove a single course code from the ones you've already entered.", false)
+        .field("`/ccimport`", "Upload the `.ics` calendar file of your schedule in a DM with the bot, so nobody else sees it, then use this command there to find your course codes from it. You'll be asked to confirm before anything is saved.", false)
+        .field("`/ccuser`", "If this user has entered their courses already, you can see them and the times/locations, if available for the course. If you've entered your courses already using `/ccupdate` it will underline similarities.", false)
+        .field("Apps → Compare schedules", "Right-click a member and pick this to compare your schedules like `/ccuser` does, visible only to you. On a message, \"Show author's schedule\" does the same for whoever sent it.", false)
+        .field("`/ccfind`", "Lists all your classes you're attending by their location, and every student in that class.", false)
+        .field("`/ccsearch`", "Search the catalog by course name, instructor, flags, location or instruction mode, e.g. `/ccsearch query:data structures days:TTH after:11:00`.", false)
+        .field("`/ccfree`", "Find when you and up to five other users are all free, e.g. `/ccfree user:@x user2:@y min_minutes:60 between:10:00-18:00`. Users with private data are left out unless they press the button to include themselves. Add `render:True` to get the windows drawn on a weekly schedule.", false)
+        .field("`/cclookup`", "Lookup a certain class code to see if anyone is taking it (async classes won't show people for now). This will list the course's times and if anyone who has entered the codes they will be listed.", false)
+        .field("`/ccnow`", "See who in this server is in class right now and where, and who is free until their next class today.", false)
+        .field("`/ccnext`", "Shows your next class, or another user's with `/ccnext user:@x`, with where it is and a countdown. Weekends, holidays and breaks between terms are skipped.", false)
+        .field("`/ccremind`", "Turn DM reminders before each of your classes on or off, e.g. `/ccremind enabled:True minutes:15`. Holidays and breaks between terms are skipped.", false)
+        .field("`/ccdigest`", "Turn a morning DM listing the day's classes, their locations and the breaks between them on or off, e.g. `/ccdigest enabled:True time:7:30`.", false)
+        .field("`/ccroom`", "See a room's weekly timetable, or every room in a building, with the students in each meeting, e.g. `/ccroom location:GDC 2.216` or `/ccroom location:GDC`.", false)
         .field("`/ccrole`", "Assign this server's supported roles based on the classes you're registered in", false)
-        .field("`/ccprivacy`", "Adjust your privacy settings to hide or share your course data with other students", false)
-        .field("`/ccdelete`", "Deletes your course codes from the bot's database, in case you don't want them there at any point.", false)
+        .field("`/ccfriend`", "Send, accept or cancel friend requests, and list your friends. Friends can see your schedule unless it's `private`, and `/ccuser` and `/ccfree` use your friends when no user is given", false)
+        .field("`/ccmydata`", "Get a JSON file of everything the bot stores about you", false)
+        .field("`/ccprivacy`", "Choose who can see your course data: everyone, people in servers you're in, or only friends. Every level except friends only still lets anyone else count you anonymously in class lists, and the most private level hides you even from friends. In a server, its `server` option also lets you hide yourself from that server. Lookups only ever show people from the server they're run in", false)
+        .field("`/ccreplies`", "Choose whether replies to your commands are only visible to you. Commands that show your own schedule or settings are hidden by default, and hidden replies have a button to share them with the channel.", false)
+        .field("`/ccdelete`", "Deletes everything the bot stores about you, after you confirm. Your courses, settings, reminders and friends are all removed.", false)
+}
+
+pub async fn autocomplete(
+    interaction: AutocompleteInteraction,
+    ctx: Context,
+) -> serenity::Result<()> {
+    let focused = interaction.data.options.iter().find(|o| o.focused);
+    let input = match focused.and_then(|o| o.value.as_ref()) {
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    };
+    let own_codes = get_user_codes(*interaction.user.id.as_u64()).unwrap_or_default();
+    let command = interaction.data.name.as_str();
+    let query = match command {
+        // only the entry after the last comma is being typed
+        "ccupdate" => input.rsplit(',').next().unwrap_or(""),
+        _ => &input,
+    };
+    let matches: Vec<&CourseData> = match command {
+        "ccremove" if query.trim().is_empty() => own_codes
+            .iter()
+            .filter_map(|c| COURSEDATA.get(c))
+            .take(25)
+            .collect(),
+        "ccremove" => search_courses(query)
+            .filter(|course| own_codes.contains(&course.code))
+            .take(25)
+            .collect(),
+        _ => search_courses(query).take(25).collect(),
+    };
+    let entered = match input.rfind(',') {
+        Some(i) => &input[..=i],
+        None => "",
+    };
+    interaction
+        .create_autocomplete_response(ctx.http, |response| {
+            for course in matches {
+                match focused.map(|o| o.name.as_str()) {
+                    Some("codes") => {
+                        if let Some(value) = code_list_choice(entered, course.code) {
+                            response.add_string_choice(course_choice_name(course), value);
+                        }
+                    }
+                    _ => {
+                        response.add_int_choice(course_choice_name(course), course.code);
+                    }
+                };
+            }
+            response
+        })
+        .await
 }
 
 pub fn unknown_command<'a>(
@@ -512,3 +3013,312 @@ pub fn unknown_command<'a>(
         .description("Use one of 5 commands: `ccupdate`, `ccuser`, `cclookup`, `ccfind`, `ccdelete`, `cchelp`, and make sure your input values are valid.")
         .color(Color::from_rgb(255, 0, 0))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, time::Instant};
+
+    use chrono::{NaiveDate, NaiveTime, TimeZone, Utc};
+    use chrono_tz::America::Chicago;
+    use serde_json::json;
+    use serenity::model::{
+        guild::PartialMember, interactions::application_command::ApplicationCommandInteraction,
+        user::User,
+    };
+
+    use super::{
+        add_code, adjacencies, attendees, cache_members, cached_members, code_list_choice,
+        conflicts_between, course_choice_name, digest_dates, display_name, location_key,
+        next_meeting, parse_codes, reminders, remove_code, target_user, Add, CourseData,
+        CourseTime, ParsedCodes, MAX_CHOICE_LENGTH, MAX_CODES, MEMBERSHIP_EXPIRY,
+    };
+    use crate::campus;
+
+    fn meeting(day: &str, start: (u32, u32), end: (u32, u32), location: &str) -> CourseTime {
+        let date = Utc.ymd(2021, 8, 25);
+        CourseTime {
+            day: Some(day.to_string()),
+            time: (
+                date.and_hms(start.0, start.1, 0),
+                date.and_hms(end.0, end.1, 0),
+            ),
+            location: Some(location.to_string()),
+        }
+    }
+
+    #[test]
+    fn finds_adjacent_meetings() {
+        let mine = meeting("MWF", (11, 0), (11, 50), "GDC 2.216");
+        let same_building = meeting("MW", (12, 30), (13, 45), "GDC 1.304");
+        let next_door = meeting("TTH", (9, 30), (10, 45), "WEL 2.224");
+        let before_next_door = meeting("TH", (8, 0), (9, 20), "RLP 0.102");
+        let far_apart = meeting("F", (15, 0), (16, 0), "GDC 4.302");
+
+        let lines = adjacencies(
+            &[&mine, &before_next_door],
+            &[&same_building, &next_door, &far_apart],
+            "X",
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "Back to back on TH: you in RLP until 09:20 AM, then X in WEL at 09:30 AM",
+                "You both have classes in GDC on MW at 11:00 AM (you) then 12:30 PM (X)",
+            ]
+        );
+    }
+
+    fn fall_2021() -> campus::Term {
+        campus::Term {
+            name: "Fall 2021".to_string(),
+            start: NaiveDate::from_ymd(2021, 8, 25),
+            end: NaiveDate::from_ymd(2021, 12, 6),
+            holidays: vec![NaiveDate::from_ymd(2021, 9, 6)],
+        }
+    }
+
+    fn course(code: i64, times: Vec<CourseTime>) -> CourseData {
+        CourseData {
+            code,
+            link: None,
+            name: None,
+            times,
+            instruction_mode: None,
+            instructor: None,
+            status: None,
+            flags: vec![],
+        }
+    }
+
+    #[test]
+    fn edits_codes_in_place() {
+        let mut codes = vec![11111, 22222];
+        assert_eq!(add_code(&mut codes, 33333), Add::Done);
+        assert_eq!(add_code(&mut codes, 22222), Add::Duplicate);
+        assert_eq!(codes, vec![11111, 22222, 33333]);
+
+        assert!(remove_code(&mut codes, 22222));
+        assert!(!remove_code(&mut codes, 22222));
+        assert_eq!(codes, vec![11111, 33333]);
+
+        let mut full: Vec<i64> = (0..MAX_CODES as i64).collect();
+        assert_eq!(add_code(&mut full, 99999), Add::Full);
+        assert_eq!(full.len(), MAX_CODES);
+    }
+
+    #[test]
+    fn reports_rejected_codes() {
+        let parsed = parse_codes("1, 2,abc, 1,,99, 3", |code| code < 10);
+        assert_eq!(
+            parsed,
+            ParsedCodes {
+                accepted: vec![1, 2, 3],
+                unknown: vec![99],
+                duplicates: vec![1],
+                dropped: vec![],
+                invalid: vec!["abc".to_string()],
+            }
+        );
+
+        let many: Vec<String> = (0..MAX_CODES as i64 + 2).map(|c| c.to_string()).collect();
+        let parsed = parse_codes(&many.join(","), |_| true);
+        assert_eq!(parsed.accepted.len(), MAX_CODES);
+        assert_eq!(parsed.dropped, vec![MAX_CODES as i64, MAX_CODES as i64 + 1]);
+    }
+
+    #[test]
+    fn flags_time_conflicts() {
+        let a = course(1, vec![meeting("MWF", (10, 0), (10, 50), "GDC 2.216")]);
+        let b = course(2, vec![meeting("WF", (10, 30), (11, 45), "WEL 2.224")]);
+        let c = course(3, vec![meeting("TTH", (10, 0), (11, 15), "GDC 1.304")]);
+        let conflicts = conflicts_between(&[&a, &b, &c]);
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].starts_with("`1` MWF | 10:00 AM-10:50 AM"));
+        assert!(conflicts[0].contains("overlaps `2` WF"));
+        assert!(conflicts_between(&[&a, &c]).is_empty());
+    }
+
+    #[test]
+    fn fits_choices_in_discord_limits() {
+        let mut long = course(12345, vec![meeting("MWF", (9, 0), (9, 50), "GDC 2.216")]);
+        long.name = Some("INTRODUCTION TO ".repeat(10));
+        long.instructor = Some("Smith".to_string());
+        let name = course_choice_name(&long);
+        assert_eq!(name.chars().count(), MAX_CHOICE_LENGTH);
+        assert!(name.starts_with("12345 — INTRODUCTION TO"));
+
+        assert_eq!(
+            code_list_choice("11111, 22222,", 33333),
+            Some("11111, 22222,33333".to_string())
+        );
+        assert_eq!(code_list_choice(&"1".repeat(96), 33333), None);
+    }
+
+    #[test]
+    fn names_users_without_a_server() {
+        let user = User::default();
+        let member: PartialMember =
+            serde_json::from_str(r#"{"nick":"Al","roles":[],"joined_at":null}"#).unwrap();
+        let no_nick: PartialMember =
+            serde_json::from_str(r#"{"nick":null,"roles":[],"joined_at":null}"#).unwrap();
+        assert_eq!(display_name(&user, Some(&member)), "Al");
+        assert_eq!(display_name(&user, Some(&no_nick)), "test");
+        assert_eq!(display_name(&user, None), "test");
+    }
+
+    #[test]
+    fn targets_message_authors() {
+        let author = json!({"id": "2", "username": "bob", "discriminator": "0002", "avatar": null});
+        let mut command = json!({
+            "id": "10",
+            "application_id": "11",
+            "type": 2,
+            "data": {
+                "id": "12",
+                "name": "Show author's schedule",
+                "type": 3,
+                "target_id": "20",
+                "resolved": {"messages": {"20": {
+                    "id": "20",
+                    "channel_id": "13",
+                    "author": author,
+                    "content": "",
+                    "timestamp": "2021-08-25T00:00:00+00:00",
+                    "edited_timestamp": null,
+                    "tts": false,
+                    "mention_everyone": false,
+                    "mentions": [],
+                    "mention_roles": [],
+                    "attachments": [],
+                    "embeds": [],
+                    "pinned": false,
+                    "type": 0,
+                }}},
+            },
+            "channel_id": "13",
+            "user": {"id": "1", "username": "alice", "discriminator": "0001", "avatar": null},
+            "token": "token",
+            "version": 1,
+            "locale": "en-US",
+        });
+        let in_dm: ApplicationCommandInteraction = serde_json::from_value(command.clone()).unwrap();
+        let (user, member, in_guild) = target_user(&in_dm).unwrap();
+        assert_eq!(user.name, "bob");
+        assert!(member.is_none() && !in_guild);
+
+        // the author posted in the server, even though no member was sent
+        command["guild_id"] = json!("14");
+        let in_server: ApplicationCommandInteraction = serde_json::from_value(command).unwrap();
+        let (user, member, in_guild) = target_user(&in_server).unwrap();
+        assert_eq!(user.name, "bob");
+        assert!(member.is_none() && in_guild);
+    }
+
+    #[test]
+    fn finds_next_meeting() {
+        let lecture = course(1, vec![meeting("MWF", (10, 0), (10, 50), "GDC 2.216")]);
+        let lab = course(2, vec![meeting("TH", (14, 0), (16, 0), "GDC 1.304")]);
+        let courses = [&lecture, &lab];
+        let terms = [fall_2021()];
+        let at = |day, hour| Chicago.ymd(2021, 8, day).and_hms(hour, 0, 0);
+        let next = |from| next_meeting(&courses, &terms, from).map(|(start, c, _)| (start, c.code));
+
+        // Wednesday the 25th, after the lecture: the lab on Thursday is next
+        assert_eq!(next(at(25, 11)), Some((at(26, 14), 2)));
+        assert_eq!(next(at(27, 9)), Some((at(27, 10), 1)));
+        // the weekend is skipped
+        assert_eq!(next(at(27, 10)), Some((at(30, 10), 1)));
+        assert_eq!(
+            next_meeting(&[], &terms, at(25, 11)).map(|(start, _, _)| start),
+            None
+        );
+        // nothing before the term starts, or on a holiday
+        assert_eq!(next(at(20, 9)), Some((at(25, 10), 1)));
+        let labor_day_weekend = Chicago.ymd(2021, 9, 3).and_hms(11, 0, 0);
+        assert_eq!(
+            next(labor_day_weekend),
+            Some((Chicago.ymd(2021, 9, 8).and_hms(10, 0, 0), 1))
+        );
+    }
+
+    #[test]
+    fn sends_reminders_once_ahead_of_class() {
+        let lecture = course(1, vec![meeting("MWF", (10, 0), (10, 50), "GDC 2.216")]);
+        let courses = [&lecture];
+        let terms = [fall_2021()];
+        let at = |hour, minute| Chicago.ymd(2021, 8, 25).and_hms(hour, minute, 0);
+        let due = |from, to| {
+            reminders(&courses, &terms, 10, from, to)
+                .iter()
+                .map(|(start, _, _)| *start)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(due(at(9, 49), at(9, 50)), vec![at(10, 0)]);
+        // consecutive ticks share a boundary without sending it twice
+        assert!(due(at(9, 48), at(9, 49)).is_empty());
+        assert!(due(at(9, 50), at(9, 51)).is_empty());
+        // catching up after an outage still sends it before class starts
+        assert_eq!(due(at(9, 40), at(9, 55)), vec![at(10, 0)]);
+        // but not once class has started
+        assert!(due(at(9, 45), at(10, 0)).is_empty());
+        // nor on a holiday
+        let labor_day = |hour, minute| Chicago.ymd(2021, 9, 6).and_hms(hour, minute, 0);
+        assert!(due(labor_day(9, 49), labor_day(9, 50)).is_empty());
+    }
+
+    #[test]
+    fn sends_digests_once_a_day() {
+        let lecture = course(1, vec![meeting("MWF", (10, 0), (10, 50), "GDC 2.216")]);
+        let courses = [&lecture];
+        let terms = [fall_2021()];
+        let eight = NaiveTime::from_hms(8, 0, 0);
+        let at = |day, hour, minute| Chicago.ymd(2021, 8, day).and_hms(hour, minute, 0);
+        let due = |from, to| digest_dates(&courses, &terms, eight, from, to);
+        let wednesday = NaiveDate::from_ymd(2021, 8, 25);
+
+        assert_eq!(due(at(25, 7, 59), at(25, 8, 0)), vec![wednesday]);
+        assert!(due(at(25, 8, 0), at(25, 8, 1)).is_empty());
+        // a window covering the hour catches up on it
+        assert_eq!(due(at(25, 6, 0), at(25, 8, 10)), vec![wednesday]);
+        // no classes on Thursday, and none on the weekend
+        assert!(due(at(26, 7, 59), at(26, 8, 0)).is_empty());
+        assert!(due(at(28, 7, 59), at(28, 8, 0)).is_empty());
+        // nor before the term starts
+        assert!(due(at(23, 7, 59), at(23, 8, 0)).is_empty());
+    }
+
+    #[test]
+    fn normalizes_locations() {
+        let key = |b: &str, r: &str| Some((b.to_string(), r.to_string()));
+        assert_eq!(location_key("GDC 2.216"), key("GDC", "2216"));
+        assert_eq!(location_key("gdc2.216"), key("GDC", "2216"));
+        assert_eq!(location_key(" GDC 2216 "), key("GDC", "2216"));
+        assert_eq!(location_key("WEL"), key("WEL", ""));
+        assert_eq!(location_key("-"), None);
+    }
+
+    #[test]
+    fn caches_membership_until_it_expires() {
+        let now = Instant::now();
+        cache_members(7, &[(1, true), (2, false)], now);
+        let (members, unknown) = cached_members(7, HashSet::from([1, 2, 3]), now);
+        assert_eq!(members, HashSet::from([1]));
+        assert_eq!(unknown, vec![3]);
+        // other servers are looked up separately
+        assert_eq!(cached_members(8, HashSet::from([1]), now).1, vec![1]);
+        let later = now + MEMBERSHIP_EXPIRY;
+        assert_eq!(cached_members(7, HashSet::from([1]), later).1, vec![1]);
+    }
+
+    #[test]
+    fn counts_private_attendees() {
+        assert_eq!(attendees(&[1, 2], 0), "<@1> <@2>");
+        assert_eq!(attendees(&[], 5), "+5 private");
+        assert_eq!(attendees(&[1], 2), "<@1> (1 student, +2 private)");
+        assert_eq!(
+            attendees(&[1, 2, 3], 5),
+            "<@1> <@2> <@3> (3 students, +5 private)"
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use chrono::{Datelike, NaiveDateTime, Weekday};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Event {
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub start: Option<NaiveDateTime>,
+    pub end: Option<NaiveDateTime>,
+    pub days: Vec<Weekday>,
+}
+
+// Only the subset of RFC 5545 that schedule exports use: VEVENTs with
+// DTSTART/DTEND wall-clock times and an optional weekly RRULE.
+pub fn parse(input: &str) -> Vec<Event> {
+    let mut events = vec![];
+    let mut current: Option<Event> = None;
+    for line in unfold(input) {
+        let (name, params, value) = match split_property(&line) {
+            Some(parts) => parts,
+            None => continue,
+        };
+        match (name.as_str(), value.as_str()) {
+            ("BEGIN", "VEVENT") => current = Some(Event::default()),
+            ("END", "VEVENT") => {
+                if let Some(mut event) = current.take() {
+                    if event.days.is_empty() {
+                        if let Some(start) = event.start {
+                            event.days.push(start.weekday());
+                        }
+                    }
+                    events.push(event);
+                }
+            }
+            _ => {
+                if let Some(event) = current.as_mut() {
+                    match name.as_str() {
+                        "SUMMARY" => event.summary = Some(unescape(&value)),
+                        "DESCRIPTION" => event.description = Some(unescape(&value)),
+                        "LOCATION" => event.location = Some(unescape(&value)),
+                        "DTSTART" => event.start = parse_date_time(&params, &value),
+                        "DTEND" => event.end = parse_date_time(&params, &value),
+                        "RRULE" => event.days = parse_by_day(&value),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    events
+}
+
+fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in input.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(&line[1..]);
+                continue;
+            }
+        }
+        lines.push(line.to_string());
+    }
+    lines
+}
+
+fn split_property(line: &str) -> Option<(String, String, String)> {
+    let mut in_quotes = false;
+    let colon = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            in_quotes = !in_quotes;
+        }
+        *c == ':' && !in_quotes
+    })?;
+    let (head, value) = (&line[..colon.0], &line[colon.0 + 1..]);
+    let (name, params) = match head.find(';') {
+        Some(i) => (&head[..i], &head[i + 1..]),
+        None => (head, ""),
+    };
+    Some((
+        name.to_uppercase(),
+        params.to_uppercase(),
+        value.to_string(),
+    ))
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+fn parse_date_time(params: &str, value: &str) -> Option<NaiveDateTime> {
+    // UTC (`Z`) and TZID times are both taken as wall-clock times, which is
+    // how the course catalog stores meetings too.
+    let value = value.trim().trim_end_matches('Z');
+    if params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME") {
+        return None;
+    }
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()
+}
+
+fn parse_by_day(rule: &str) -> Vec<Weekday> {
+    rule.split(';')
+        .filter_map(|part| part.strip_prefix("BYDAY="))
+        .flat_map(|days| days.split(','))
+        .filter_map(|day| {
+            let day = day.trim();
+            // strip ordinal prefixes such as `1MO` or `-1FR`
+            match &day[day.len().saturating_sub(2)..] {
+                "MO" => Some(Weekday::Mon),
+                "TU" => Some(Weekday::Tue),
+                "WE" => Some(Weekday::Wed),
+                "TH" => Some(Weekday::Thu),
+                "FR" => Some(Weekday::Fri),
+                "SA" => Some(Weekday::Sat),
+                "SU" => Some(Weekday::Sun),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, Weekday};
+
+    use super::parse;
+
+    #[test]
+    fn parses_weekly_events() {
+        let input = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:C S 314 DATA STRUCTURES\\, Lecture\r\n\
+            LOCATION:GDC 2.216\r\n\
+            DESCRIPTION:Unique 50850\r\n \
+            with Smith\r\n\
+            DTSTART;TZID=America/Chicago:20210825T100000\r\n\
+            DTEND;TZID=America/Chicago:20210825T110000\r\n\
+            RRULE:FREQ=WEEKLY;UNTIL=20211206T000000Z;BYDAY=MO,WE,FR\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:Lab\r\n\
+            DTSTART:20210826T140000Z\r\n\
+            DTEND:20210826T150000Z\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        let events = parse(input);
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0].summary.as_deref(),
+            Some("C S 314 DATA STRUCTURES, Lecture")
+        );
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("Unique 50850with Smith")
+        );
+        assert_eq!(events[0].location.as_deref(), Some("GDC 2.216"));
+        assert_eq!(
+            events[0].start,
+            Some(NaiveDate::from_ymd(2021, 8, 25).and_hms(10, 0, 0))
+        );
+        assert_eq!(
+            events[0].days,
+            vec![Weekday::Mon, Weekday::Wed, Weekday::Fri]
+        );
+        assert_eq!(events[1].days, vec![Weekday::Thu]);
+        assert_eq!(
+            events[1].end,
+            Some(NaiveDate::from_ymd(2021, 8, 26).and_hms(15, 0, 0))
+        );
+    }
+}
@@ -1,55 +1,134 @@
+mod buildings;
+mod campus;
+mod data;
+mod deferred;
+mod error;
+mod free;
+mod friends;
 mod handlers;
+mod ics;
+mod pagination;
+mod preferences;
+mod privacy;
+mod scheduler;
+mod search;
 
 use std::env;
 
 use serenity::{
     async_trait,
+    builder::CreateEmbed,
     model::{
         gateway::Ready,
         interactions::{
-            application_command::{ApplicationCommand, ApplicationCommandOptionType},
-            Interaction, InteractionResponseType,
+            application_command::{
+                ApplicationCommand, ApplicationCommandInteraction, ApplicationCommandOptionType,
+                ApplicationCommandType,
+            },
+            message_component::MessageComponentInteraction,
+            Interaction,
         },
     },
     prelude::*,
 };
 
+use crate::error::BotResult;
+
 struct Handler;
 
+async fn run_command(command: ApplicationCommandInteraction, ctx: &Context) -> BotResult<()> {
+    let mut embed = CreateEmbed::default();
+    match command.data.name.as_str() {
+        "ccfind" => {
+            let work = handlers::ccfind(command.clone(), ctx.http.clone());
+            return deferred::spawn(&command, ctx, work).await;
+        }
+        "ccroom" => {
+            let work = handlers::ccroom(command.clone(), ctx.http.clone());
+            return deferred::spawn(&command, ctx, work).await;
+        }
+        "cclookup" => {
+            let work = handlers::cclookup(command.clone(), ctx.http.clone());
+            return deferred::spawn(&command, ctx, work).await;
+        }
+        "ccimport" => {
+            let work = handlers::ccimport(command.clone(), ctx.http.clone());
+            return deferred::spawn_with_buttons(&command, ctx, work).await;
+        }
+        "ccfree" => return handlers::ccfree(command, ctx.clone()).await,
+        "ccdelete" => return handlers::ccdelete(command, ctx.clone()).await,
+        "ccrole" => {
+            let work = handlers::ccrole(command.clone(), ctx.http.clone());
+            return deferred::spawn(&command, ctx, work).await;
+        }
+        "ccuser" => {
+            let work = handlers::ccuser(command.clone(), ctx.http.clone());
+            return deferred::spawn(&command, ctx, work).await;
+        }
+        "ccfriend" => {
+            let work = handlers::ccfriend(command.clone(), ctx.http.clone());
+            return deferred::spawn(&command, ctx, work).await;
+        }
+        "ccmydata" => {
+            let work = handlers::ccmydata(command.clone(), ctx.http.clone());
+            return deferred::spawn(&command, ctx, work).await;
+        }
+        "ccnow" => {
+            let work = handlers::ccnow(command.clone(), ctx.http.clone());
+            return deferred::spawn(&command, ctx, work).await;
+        }
+        "Compare schedules" | "Show author's schedule" => {
+            handlers::ccuser_target(&mut embed, &command)?
+        }
+        "ccsearch" => handlers::ccsearch(&mut embed, &command)?,
+        "ccnext" => handlers::ccnext(&mut embed, &command)?,
+        "ccremind" => handlers::ccremind(&mut embed, &command)?,
+        "ccdigest" => handlers::ccdigest(&mut embed, &command)?,
+        "ccupdate" => handlers::ccupdate(&mut embed, &command)?,
+        "ccadd" => handlers::ccadd(&mut embed, &command)?,
+        "ccremove" => handlers::ccremove(&mut embed, &command)?,
+        "cchelp" => handlers::cchelp(&mut embed, &command),
+        "ccprivacy" => handlers::ccprivacy(&mut embed, &command)?,
+        "ccreplies" => handlers::ccreplies(&mut embed, &command)?,
+        _ => handlers::unknown_command(&mut embed, &command),
+    };
+    pagination::respond(&command, ctx, vec![embed]).await?;
+    Ok(())
+}
+
+async fn run_component(component: MessageComponentInteraction, ctx: &Context) -> BotResult<()> {
+    match component.data.custom_id.split(':').next() {
+        Some("ccimport") => handlers::ccimport_component(component, ctx.clone()).await,
+        Some("ccfree") => handlers::ccfree_component(component, ctx.clone()).await,
+        Some("ccdelete") => handlers::ccdelete_component(component, ctx.clone()).await,
+        Some("ccfriend") => handlers::ccfriend_component(component, ctx.clone()).await,
+        Some("page") => Ok(pagination::handle(component, ctx.clone()).await?),
+        Some("share") => Ok(pagination::share(component, ctx.clone()).await?),
+        _ => Ok(()),
+    }
+}
+
 #[async_trait]
 impl EventHandler for Handler {
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         if let Interaction::ApplicationCommand(command) = interaction {
-            if let Err(why) = match command.data.name.as_str() {
-                "ccfind" => handlers::ccfind(command, ctx).await,
-                "ccrole" => handlers::ccrole(command, ctx).await,
-                _ => {
-                    command
-                        .create_interaction_response(&ctx.http, |response| {
-                            response
-                                .kind(InteractionResponseType::ChannelMessageWithSource)
-                                .interaction_response_data(|message| {
-                                    message.create_embed(|embed| match command.data.name.as_str() {
-                                        "ccupdate" => handlers::ccupdate(embed, &command),
-                                        "ccuser" => handlers::ccuser(embed, &command),
-                                        "cclookup" => handlers::cclookup(embed, &command),
-                                        "ccdelete" => handlers::ccdelete(embed, &command),
-                                        "cchelp" => handlers::cchelp(embed, &command),
-                                        "ccprivacy" => handlers::ccprivacy(embed, &command),
-                                        _ => handlers::unknown_command(embed, &command),
-                                    })
-                                })
-                        })
-                        .await
-                }
-            } {
-                println!("Cannot respond to slash command: {}", why);
+            if let Err(why) = run_command(command.clone(), &ctx).await {
+                error::respond(&command, &ctx, why).await;
+            }
+        } else if let Interaction::MessageComponent(component) = interaction {
+            if let Err(why) = run_component(component.clone(), &ctx).await {
+                error::respond_component(&component, &ctx, why).await;
+            }
+        } else if let Interaction::Autocomplete(autocomplete) = interaction {
+            if let Err(why) = handlers::autocomplete(autocomplete, ctx).await {
+                println!("Cannot respond to autocomplete: {}", why);
             }
         }
     }
 
     async fn ready(&self, ctx: Context, ready: Ready) {
         println!("{} is connected!", ready.user.name);
+        scheduler::start(ctx.http.clone());
 
         let commands = ApplicationCommand::set_global_application_commands(&ctx.http, |commands| {
             commands
@@ -63,8 +142,54 @@ impl EventHandler for Handler {
                                 .description("Comma separated unique course codes")
                                 .kind(ApplicationCommandOptionType::String)
                                 .required(true)
+                                .set_autocomplete(true)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("allow_conflicts")
+                                .description("Save even if some sections meet at the same time")
+                                .kind(ApplicationCommandOptionType::Boolean)
+                                .required(false)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("ccadd")
+                        .description("Add a course to your courses")
+                        .create_option(|option| {
+                            option
+                                .name("code")
+                                .description("Unique course code to add")
+                                .kind(ApplicationCommandOptionType::Integer)
+                                .required(true)
+                                .set_autocomplete(true)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("allow_conflicts")
+                                .description("Add it even if it meets at the same time as one of your courses")
+                                .kind(ApplicationCommandOptionType::Boolean)
+                                .required(false)
                         })
                 })
+                .create_application_command(|command| {
+                    command
+                        .name("ccremove")
+                        .description("Remove a course from your courses")
+                        .create_option(|option| {
+                            option
+                                .name("code")
+                                .description("Unique course code to remove")
+                                .kind(ApplicationCommandOptionType::Integer)
+                                .required(true)
+                                .set_autocomplete(true)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("ccimport")
+                        .description("Import your courses from the .ics calendar file you last uploaded here")
+                })
                 .create_application_command(|command| {
                     command
                         .name("ccuser")
@@ -72,9 +197,8 @@ impl EventHandler for Handler {
                         .create_option(|option| {
                             option
                                 .name("user")
-                                .description("User to compare against")
+                                .description("User to compare against; leave empty to compare with your friends")
                                 .kind(ApplicationCommandOptionType::User)
-                                .required(true)
                         })
                 })
                 .create_application_command(|command| {
@@ -87,6 +211,47 @@ impl EventHandler for Handler {
                                 .description("Valid course code")
                                 .kind(ApplicationCommandOptionType::Integer)
                                 .required(true)
+                                .set_autocomplete(true)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("ccsearch")
+                        .description("Search the course catalog")
+                        .create_option(|option| {
+                            option
+                                .name("query")
+                                .description("Course name, instructor, flag, location or mode")
+                                .kind(ApplicationCommandOptionType::String)
+                                .required(true)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("days")
+                                .description("Only courses meeting on these days, e.g. MWF or TTH")
+                                .kind(ApplicationCommandOptionType::String)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("after")
+                                .description(
+                                    "Only courses starting at or after this time, e.g. 10:00",
+                                )
+                                .kind(ApplicationCommandOptionType::String)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("before")
+                                .description(
+                                    "Only courses ending at or before this time, e.g. 17:00",
+                                )
+                                .kind(ApplicationCommandOptionType::String)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("status")
+                                .description("Only courses with this status, e.g. open")
+                                .kind(ApplicationCommandOptionType::String)
                         })
                 })
                 .create_application_command(|command| {
@@ -94,33 +259,239 @@ impl EventHandler for Handler {
                         .name("ccfind")
                         .description("Find at students in all your classes")
                 })
+                .create_application_command(|command| {
+                    command
+                        .name("ccnow")
+                        .description("See who in this server is in class or free right now")
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("ccnext")
+                        .description("See your or another user's next class")
+                        .create_option(|option| {
+                            option
+                                .name("user")
+                                .description("User whose next class to show, yourself by default")
+                                .kind(ApplicationCommandOptionType::User)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("ccremind")
+                        .description("Get a DM before each of your classes")
+                        .create_option(|option| {
+                            option
+                                .name("enabled")
+                                .description("Whether to send reminders")
+                                .kind(ApplicationCommandOptionType::Boolean)
+                                .required(true)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("minutes")
+                                .description("How many minutes before class, 10 by default")
+                                .kind(ApplicationCommandOptionType::Integer)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("ccdigest")
+                        .description("Get a DM each weekday morning listing that day's classes")
+                        .create_option(|option| {
+                            option
+                                .name("enabled")
+                                .description("Whether to send the digest")
+                                .kind(ApplicationCommandOptionType::Boolean)
+                                .required(true)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("time")
+                                .description(
+                                    "Campus time to send it at, like 7:30, 7:30 by default",
+                                )
+                                .kind(ApplicationCommandOptionType::String)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("ccroom")
+                        .description("See the timetable of a room or building")
+                        .create_option(|option| {
+                            option
+                                .name("location")
+                                .description("Building code or room, like GDC or GDC 2.216")
+                                .kind(ApplicationCommandOptionType::String)
+                                .required(true)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("ccfree")
+                        .description("Find times when you and other users are all free")
+                        .create_option(|option| {
+                            option
+                                .name("user")
+                                .description("User to find free time with; leave empty to use your friends")
+                                .kind(ApplicationCommandOptionType::User)
+                        });
+                    for name in ["user2", "user3", "user4", "user5"] {
+                        command.create_option(|option| {
+                            option
+                                .name(name)
+                                .description("Another user to find free time with")
+                                .kind(ApplicationCommandOptionType::User)
+                        });
+                    }
+                    command
+                        .create_option(|option| {
+                            option
+                                .name("min_minutes")
+                                .description("Shortest window worth showing, 30 minutes by default")
+                                .kind(ApplicationCommandOptionType::Integer)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("between")
+                                .description("Only look between these times, like 9:00-17:00")
+                                .kind(ApplicationCommandOptionType::String)
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("render")
+                                .description("Also send the windows drawn on a weekly schedule")
+                                .kind(ApplicationCommandOptionType::Boolean)
+                        })
+                })
                 .create_application_command(|command| {
                     command
                         .name("ccdelete")
-                        .description("Delete your course information")
+                        .description("Delete everything the bot stores about you")
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("ccmydata")
+                        .description("Get a copy of everything the bot stores about you")
                 })
                 .create_application_command(|command| {
                     command
                         .name("ccrole")
                         .description("Assign roles based on your classes")
                 })
+                .create_application_command(|command| {
+                    command
+                        .name("ccfriend")
+                        .description("Manage who can see your schedule as a friend")
+                        .create_option(|option| {
+                            option
+                                .name("add")
+                                .description("Send a friend request, or accept one")
+                                .kind(ApplicationCommandOptionType::SubCommand)
+                                .create_sub_option(|option| {
+                                    option
+                                        .name("user")
+                                        .description("User to befriend")
+                                        .kind(ApplicationCommandOptionType::User)
+                                        .required(true)
+                                })
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("remove")
+                                .description("Remove a friend, or cancel or decline a request")
+                                .kind(ApplicationCommandOptionType::SubCommand)
+                                .create_sub_option(|option| {
+                                    option
+                                        .name("user")
+                                        .description("User to remove")
+                                        .kind(ApplicationCommandOptionType::User)
+                                        .required(true)
+                                })
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("list")
+                                .description("List your friends and pending requests")
+                                .kind(ApplicationCommandOptionType::SubCommand)
+                        })
+                })
                 .create_application_command(|command| {
                     command
                         .name("ccprivacy")
-                        .description("Make your course data private/public")
+                        .description("Choose who can see your course data")
                         .create_option(|option| {
                             option
-                                .name("private")
-                                .description("Make course data private/public")
+                                .name("level")
+                                .description("Who can see your course data")
+                                .kind(ApplicationCommandOptionType::String)
+                                .add_string_choice("Everyone", "public")
+                                .add_string_choice("People in servers you're in", "members")
+                                .add_string_choice("Friends only", "friends")
+                                .add_string_choice("Friends, and anonymous headcounts for others", "counts")
+                                .add_string_choice("Only anonymous headcounts", "private")
+                        })
+                        .create_option(|option| {
+                            option
+                                .name("server")
+                                .description("Whether people in this server can see you at all")
+                                .kind(ApplicationCommandOptionType::Boolean)
+                        })
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("ccreplies")
+                        .description(
+                            "Choose whether the bot's replies to you are only visible to you",
+                        )
+                        .create_option(|option| {
+                            option
+                                .name("hidden")
+                                .description("Only show replies to you")
                                 .kind(ApplicationCommandOptionType::Boolean)
                                 .required(true)
                         })
+                        .create_option(|option| {
+                            option
+                                .name("command")
+                                .description("Command to change, all commands if left out")
+                                .kind(ApplicationCommandOptionType::String)
+                                .add_string_choice("ccupdate", "ccupdate")
+                                .add_string_choice("ccadd", "ccadd")
+                                .add_string_choice("ccremove", "ccremove")
+                                .add_string_choice("ccimport", "ccimport")
+                                .add_string_choice("ccuser", "ccuser")
+                                .add_string_choice("ccsearch", "ccsearch")
+                                .add_string_choice("cclookup", "cclookup")
+                                .add_string_choice("ccfind", "ccfind")
+                                .add_string_choice("ccrole", "ccrole")
+                                .add_string_choice("ccprivacy", "ccprivacy")
+                                .add_string_choice("ccdelete", "ccdelete")
+                                .add_string_choice("ccmydata", "ccmydata")
+                                .add_string_choice("ccfree", "ccfree")
+                                .add_string_choice("ccnow", "ccnow")
+                                .add_string_choice("ccnext", "ccnext")
+                                .add_string_choice("ccremind", "ccremind")
+                                .add_string_choice("ccdigest", "ccdigest")
+                                .add_string_choice("ccroom", "ccroom")
+                                .add_string_choice("ccfriend", "ccfriend")
+                                .add_string_choice("cchelp", "cchelp")
+                        })
                 })
                 .create_application_command(|command| {
                     command
                         .name("cchelp")
                         .description("Learn about the bot and its commands")
                 })
+                .create_application_command(|command| {
+                    command
+                        .name("Compare schedules")
+                        .kind(ApplicationCommandType::User)
+                })
+                .create_application_command(|command| {
+                    command
+                        .name("Show author's schedule")
+                        .kind(ApplicationCommandType::Message)
+                })
         })
         .await;
 
@@ -142,6 +513,16 @@ async fn main() {
         .parse()
         .expect("application id is not a valid id");
 
+    // Load the catalog, other data files and databases now so a broken one stops the bot here
+    // instead of failing the first command that needs it.
+    handlers::load_catalog();
+    campus::load();
+    buildings::load();
+    privacy::load();
+    preferences::load();
+    friends::load();
+    scheduler::load();
+
     // Build our client.
     let mut client = Client::builder(token)
         .event_handler(Handler)
@@ -0,0 +1,390 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use serde_json::Value;
+use serenity::{
+    builder::{CreateComponents, CreateEmbed},
+    client::Context,
+    model::{
+        id::UserId,
+        interactions::{
+            application_command::ApplicationCommandInteraction,
+            message_component::{ButtonStyle, MessageComponentInteraction},
+            InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
+        },
+    },
+};
+
+use crate::{error::BotResult, preferences};
+
+const MAX_FIELDS: usize = 25;
+const MAX_FIELD_NAME: usize = 256;
+const MAX_FIELD_VALUE: usize = 1024;
+const MAX_TITLE: usize = 256;
+const MAX_DESCRIPTION: usize = 4096;
+const MAX_FOOTER: usize = 2048;
+const MAX_TOTAL: usize = 6000;
+// room left in the footer for "Page x of y"
+const PAGE_LABEL: usize = 32;
+// interaction tokens are only valid for 15 minutes anyway
+const EXPIRY: Duration = Duration::from_secs(15 * 60);
+
+struct Pages {
+    owner: UserId,
+    embeds: Vec<CreateEmbed>,
+    shareable: bool,
+    expires: Instant,
+}
+
+lazy_static! {
+    static ref PAGES: Mutex<HashMap<u64, Pages>> = Mutex::new(HashMap::new());
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((i, _)) => s[..i].to_string(),
+        None => s.to_string(),
+    }
+}
+
+fn text_of(embed: &CreateEmbed, key: &str) -> String {
+    match embed.0.get(key) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Object(map)) => map
+            .get("text")
+            .or_else(|| map.get("name"))
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string(),
+        _ => String::new(),
+    }
+}
+
+// Splits a field value into pieces no longer than `max` characters, breaking
+// at lines first, then spaces, then anywhere.
+fn chunk(value: &str, max: usize) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current = String::new();
+    let mut push = |piece: &str, separator: &str, current: &mut String| {
+        let len = current.chars().count();
+        if len > 0 && len + separator.len() + piece.chars().count() > max {
+            chunks.push(std::mem::take(current));
+        }
+        if !current.is_empty() {
+            current.push_str(separator);
+        }
+        current.push_str(piece);
+    };
+    for line in value.split('\n') {
+        if line.chars().count() <= max {
+            push(line, "\n", &mut current);
+            continue;
+        }
+        for word in line.split(' ') {
+            let chars: Vec<char> = word.chars().collect();
+            for piece in chars.chunks(max) {
+                push(&piece.iter().collect::<String>(), " ", &mut current);
+            }
+        }
+    }
+    chunks.push(current);
+    chunks
+}
+
+// Breaks every embed into as many embeds as it takes to stay within
+// Discord's per-field, per-embed and per-message limits.
+pub fn split(embeds: Vec<CreateEmbed>) -> Vec<CreateEmbed> {
+    let mut pages = vec![];
+    for mut base in embeds {
+        let fields = match base.0.remove("fields") {
+            Some(Value::Array(fields)) => fields,
+            _ => vec![],
+        };
+        let title = truncate(&text_of(&base, "title"), MAX_TITLE);
+        let description = truncate(&text_of(&base, "description"), MAX_DESCRIPTION);
+        let footer = truncate(&text_of(&base, "footer"), MAX_FOOTER - PAGE_LABEL);
+        if base.0.contains_key("title") {
+            base.title(&title);
+        }
+        if base.0.contains_key("description") {
+            base.description(&description);
+        }
+        if base.0.contains_key("footer") {
+            base.footer(|f| f.text(&footer));
+        }
+        let base_len = [title, description, footer, text_of(&base, "author")]
+            .iter()
+            .map(|s| s.chars().count())
+            .sum::<usize>()
+            + PAGE_LABEL;
+
+        let mut page = base.clone();
+        let (mut len, mut count) = (base_len, 0);
+        for field in fields {
+            let name = field["name"].as_str().unwrap_or("").to_string();
+            let inline = field["inline"].as_bool().unwrap_or(false);
+            let value = field["value"].as_str().unwrap_or("");
+            for (i, value) in chunk(value, MAX_FIELD_VALUE).into_iter().enumerate() {
+                let name = match i {
+                    0 => truncate(&name, MAX_FIELD_NAME),
+                    _ => truncate(&format!("{} (cont.)", name), MAX_FIELD_NAME),
+                };
+                let field_len = name.chars().count() + value.chars().count();
+                if count == MAX_FIELDS || (count > 0 && len + field_len > MAX_TOTAL) {
+                    pages.push(std::mem::replace(&mut page, base.clone()));
+                    len = base_len;
+                    count = 0;
+                }
+                page.field(name, value, inline);
+                len += field_len;
+                count += 1;
+            }
+        }
+        pages.push(page);
+    }
+    pages
+}
+
+fn render(embed: &CreateEmbed, index: usize, total: usize) -> CreateEmbed {
+    let mut embed = embed.clone();
+    if total > 1 {
+        let footer = text_of(&embed, "footer");
+        let label = format!("Page {} of {}", index + 1, total);
+        embed.footer(|f| match footer.is_empty() {
+            true => f.text(label),
+            false => f.text(format!("{}\n{}", footer, label)),
+        });
+    }
+    embed
+}
+
+// Prev/Next for results with several pages, and a way to post replies only
+// the caller can see to the channel when they hold only the caller's data.
+fn buttons(
+    components: &mut CreateComponents,
+    key: u64,
+    index: usize,
+    total: usize,
+    shareable: bool,
+) -> &mut CreateComponents {
+    if total < 2 && !shareable {
+        return components;
+    }
+    components.create_action_row(|row| {
+        if total > 1 {
+            row.create_button(|button| {
+                button
+                    .style(ButtonStyle::Secondary)
+                    .label("Prev")
+                    .custom_id(format!("page:{}:{}", key, index.saturating_sub(1)))
+                    .disabled(index == 0)
+            })
+            .create_button(|button| {
+                button
+                    .style(ButtonStyle::Secondary)
+                    .label("Next")
+                    .custom_id(format!("page:{}:{}", key, index + 1))
+                    .disabled(index + 1 >= total)
+            });
+        }
+        if shareable {
+            row.create_button(|button| {
+                button
+                    .style(ButtonStyle::Primary)
+                    .label("Share to channel")
+                    .custom_id("share")
+            });
+        }
+        row
+    })
+}
+
+// Stores the pages past the first so the buttons can get to them later.
+fn prepare(
+    command: &ApplicationCommandInteraction,
+    embeds: Vec<CreateEmbed>,
+    shareable: bool,
+) -> (CreateEmbed, u64, usize) {
+    let pages = split(embeds);
+    let total = pages.len();
+    let first = render(&pages[0], 0, total);
+    let key = *command.id.as_u64();
+    if total > 1 {
+        let mut stored = PAGES.lock().unwrap();
+        let now = Instant::now();
+        stored.retain(|_, p| p.expires > now);
+        stored.insert(
+            key,
+            Pages {
+                owner: command.user.id,
+                embeds: pages,
+                shareable,
+                expires: now + EXPIRY,
+            },
+        );
+    }
+    (first, key, total)
+}
+
+pub async fn respond(
+    command: &ApplicationCommandInteraction,
+    ctx: &Context,
+    embeds: Vec<CreateEmbed>,
+) -> BotResult<()> {
+    let ephemeral = preferences::is_ephemeral(command)?;
+    let shareable = ephemeral && preferences::shows_only_caller(command);
+    let (first, key, total) = prepare(command, embeds, shareable);
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    if ephemeral {
+                        message.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+                    }
+                    message
+                        .add_embed(first)
+                        .components(|c| buttons(c, key, 0, total, shareable))
+                })
+        })
+        .await?;
+    Ok(())
+}
+
+// Same as `respond`, for interactions that were already deferred.
+pub async fn edit_response(
+    command: &ApplicationCommandInteraction,
+    ctx: &Context,
+    embeds: Vec<CreateEmbed>,
+) -> BotResult<()> {
+    let shareable = preferences::is_ephemeral(command)? && preferences::shows_only_caller(command);
+    let (first, key, total) = prepare(command, embeds, shareable);
+    command
+        .edit_original_interaction_response(&ctx.http, |response| {
+            response
+                .add_embed(first)
+                .components(|c| buttons(c, key, 0, total, shareable))
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn handle(component: MessageComponentInteraction, ctx: Context) -> serenity::Result<()> {
+    // custom ids look like `page:<command interaction id>:<page index>`
+    let mut parts = component.data.custom_id.split(':').skip(1);
+    let key: u64 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+    let index: usize = parts.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+    let page = {
+        let mut stored = PAGES.lock().unwrap();
+        let now = Instant::now();
+        stored.retain(|_, p| p.expires > now);
+        stored.get(&key).map(|p| {
+            (
+                p.owner,
+                p.embeds.get(index).cloned(),
+                p.embeds.len(),
+                p.shareable,
+            )
+        })
+    };
+    let (embed, total, shareable) = match page {
+        Some((owner, Some(embed), total, shareable)) if owner == component.user.id => {
+            (embed, total, shareable)
+        }
+        page => {
+            let notice = match page {
+                None => "These results have expired. Run the command again to see more.",
+                Some((_, Some(_), _, _)) => "Only the user who ran this command can change pages.",
+                Some((_, None, _, _)) => "That page doesn't exist.",
+            };
+            return component
+                .create_interaction_response(&ctx.http, |response| {
+                    response
+                        .kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|message| {
+                            message
+                                .flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                                .content(notice)
+                        })
+                })
+                .await;
+        }
+    };
+    let embed = render(&embed, index, total);
+    component
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|message| {
+                    message
+                        .embeds(vec![embed])
+                        .components(|c| buttons(c, key, index, total, shareable))
+                })
+        })
+        .await
+}
+
+// Reposts a reply only the caller could see so the whole channel sees it.
+pub async fn share(component: MessageComponentInteraction, ctx: Context) -> serenity::Result<()> {
+    let embeds: Vec<CreateEmbed> = component
+        .message
+        .embeds
+        .iter()
+        .cloned()
+        .map(CreateEmbed::from)
+        .collect();
+    component
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    message
+                        .content(format!("Shared by <@{}>", component.user.id))
+                        .embeds(embeds)
+                })
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::builder::CreateEmbed;
+
+    use super::{split, MAX_FIELDS, MAX_FIELD_VALUE};
+
+    #[test]
+    fn splits_oversized_embeds() {
+        let mut many_fields = CreateEmbed::default();
+        many_fields.title("Many").footer(|f| f.text("note"));
+        for i in 0..(MAX_FIELDS + 5) {
+            many_fields.field(i, "value", false);
+        }
+        let mut long_field = CreateEmbed::default();
+        long_field.title("Long");
+        let mentions: Vec<String> = (0..200)
+            .map(|i| format!("<@{}>", 100_000_000 + i))
+            .collect();
+        long_field.field("Mentions", mentions.join(" "), false);
+
+        let pages = split(vec![many_fields, long_field]);
+        let field_counts: Vec<usize> = pages
+            .iter()
+            .map(|p| p.0["fields"].as_array().unwrap().len())
+            .collect();
+        assert_eq!(field_counts, vec![MAX_FIELDS, 5, 3]);
+        assert_eq!(pages[1].0["title"], "Many");
+        assert_eq!(pages[1].0["footer"]["text"], "note");
+        let long_fields = pages[2].0["fields"].as_array().unwrap();
+        assert_eq!(long_fields[1]["name"], "Mentions (cont.)");
+        let rejoined: Vec<&str> = long_fields
+            .iter()
+            .map(|f| f["value"].as_str().unwrap())
+            .inspect(|v| assert!(v.chars().count() <= MAX_FIELD_VALUE))
+            .collect();
+        assert_eq!(rejoined.join(" "), mentions.join(" "));
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serenity::model::interactions::application_command::{
+    ApplicationCommandInteraction, ApplicationCommandInteractionDataOption,
+    ApplicationCommandInteractionDataOptionValue, ApplicationCommandType,
+};
+
+use crate::{data, error::BotResult};
+
+// Commands whose replies show the caller's own schedule or settings. These
+// are only visible to the caller unless they choose otherwise.
+const PERSONAL_COMMANDS: [&str; 12] = [
+    "ccupdate",
+    "ccadd",
+    "ccremove",
+    "ccimport",
+    "ccfind",
+    "ccprivacy",
+    "ccdelete",
+    "ccreplies",
+    "ccremind",
+    "ccdigest",
+    "ccfriend",
+    "ccmydata",
+];
+
+// Personal commands whose replies also name other users.
+const MENTIONS_OTHERS: [&str; 2] = ["ccfind", "ccfriend"];
+
+lazy_static! {
+    static ref PREFERENCEDB: sled::Db =
+        sled::open(std::env::var("PREFERENCEDB").unwrap_or("./preferences.db".to_string()))
+            .expect("Expected to open the PREFERENCEDB database, ./preferences.db by default");
+}
+
+pub fn load() {
+    lazy_static::initialize(&PREFERENCEDB);
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Preferences {
+    // keyed by command name, or "all" for every command
+    hidden: HashMap<String, bool>,
+}
+
+pub fn store() -> data::Store<'static> {
+    store_in(&PREFERENCEDB)
+}
+
+pub fn store_in(tree: &sled::Tree) -> data::Store<'_> {
+    data::Store {
+        name: "preferences",
+        tree,
+        read: data::read_as::<Preferences>,
+        forget: None,
+    }
+}
+
+fn get(uid: u64) -> BotResult<Preferences> {
+    match PREFERENCEDB.get(uid.to_be_bytes())? {
+        Some(ivec) => Ok(serde_json::from_slice(&ivec)?),
+        None => Ok(Preferences::default()),
+    }
+}
+
+pub fn set_hidden(uid: u64, command: Option<&str>, hidden: bool) -> BotResult<()> {
+    let mut preferences = get(uid)?;
+    match command {
+        Some(command) => {
+            preferences.hidden.insert(command.to_string(), hidden);
+        }
+        None => {
+            preferences.hidden.clear();
+            preferences.hidden.insert("all".to_string(), hidden);
+        }
+    }
+    PREFERENCEDB.insert(uid.to_be_bytes(), serde_json::to_vec(&preferences)?)?;
+    Ok(())
+}
+
+pub fn is_ephemeral(command: &ApplicationCommandInteraction) -> BotResult<bool> {
+    // nobody else can see replies in DMs anyway
+    if command.guild_id.is_none() {
+        return Ok(false);
+    }
+    // context menu commands can't take options, so they always reply privately
+    if command.data.kind != ApplicationCommandType::ChatInput {
+        return Ok(true);
+    }
+    let name = command.data.name.as_str();
+    let own_data = match name {
+        "ccuser" | "ccnext" => shows_only_caller(command),
+        _ => false,
+    };
+    Ok(hidden(&get(*command.user.id.as_u64())?, name, own_data))
+}
+
+// Whether the reply to `command` holds nothing but the caller's own data, so
+// posting it to the channel can't reveal anyone else's.
+pub fn shows_only_caller(command: &ApplicationCommandInteraction) -> bool {
+    if command.data.kind != ApplicationCommandType::ChatInput {
+        return false;
+    }
+    let is_caller = |o: &ApplicationCommandInteractionDataOption| match &o.resolved {
+        Some(ApplicationCommandInteractionDataOptionValue::User(user, _)) => {
+            user.id == command.user.id
+        }
+        _ => false,
+    };
+    match command.data.name.as_str() {
+        "ccuser" => command.data.options.iter().any(is_caller),
+        // without a user it shows the caller's own next class
+        "ccnext" => command.data.options.iter().all(is_caller),
+        name => PERSONAL_COMMANDS.contains(&name) && !MENTIONS_OTHERS.contains(&name),
+    }
+}
+
+// A per-command choice beats a choice for every command, which beats the
+// default of hiding personal data.
+fn hidden(preferences: &Preferences, name: &str, own_data: bool) -> bool {
+    match preferences
+        .hidden
+        .get(name)
+        .or_else(|| preferences.hidden.get("all"))
+    {
+        Some(hidden) => *hidden,
+        None => own_data || PERSONAL_COMMANDS.contains(&name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use serenity::model::interactions::application_command::ApplicationCommandInteraction;
+
+    use super::{hidden, is_ephemeral, shows_only_caller, Preferences};
+
+    // `kind` is 1 for slash commands and 2 for user context menu commands,
+    // which target the caller here
+    fn command(name: &str, kind: u8, guild: bool) -> ApplicationCommandInteraction {
+        let user = json!({"id": "1", "username": "alice", "discriminator": "0001", "avatar": null});
+        let mut data = json!({"id": "12", "name": name, "type": kind});
+        if kind != 1 {
+            data["target_id"] = Value::from("1");
+            data["resolved"] = json!({"users": {"1": user}});
+        }
+        let mut command = json!({
+            "id": "10",
+            "application_id": "11",
+            "type": 2,
+            "data": data,
+            "channel_id": "13",
+            "user": user,
+            "token": "token",
+            "version": 1,
+            "locale": "en-US",
+        });
+        if guild {
+            command["guild_id"] = Value::from("14");
+            command["member"] = json!({
+                "user": user,
+                "roles": [],
+                "joined_at": "2021-08-25T00:00:00+00:00",
+                "deaf": false,
+                "mute": false,
+            });
+        }
+        serde_json::from_value(command).unwrap()
+    }
+
+    #[test]
+    fn replies_openly_in_dms() {
+        // nothing here reads the stored preferences
+        assert!(!is_ephemeral(&command("ccfind", 1, false)).unwrap());
+        assert!(!is_ephemeral(&command("ccmydata", 1, false)).unwrap());
+        assert!(!is_ephemeral(&command("Compare schedules", 2, false)).unwrap());
+    }
+
+    #[test]
+    fn hides_context_menu_replies() {
+        assert!(is_ephemeral(&command("Compare schedules", 2, true)).unwrap());
+    }
+
+    #[test]
+    fn shares_only_the_callers_own_data() {
+        assert!(shows_only_caller(&command("ccadd", 1, true)));
+        assert!(shows_only_caller(&command("ccnext", 1, true)));
+        assert!(!shows_only_caller(&command("ccfind", 1, true)));
+        assert!(!shows_only_caller(&command("cclookup", 1, true)));
+        // without a user, `/ccuser` compares against friends
+        assert!(!shows_only_caller(&command("ccuser", 1, true)));
+        assert!(!shows_only_caller(&command("Compare schedules", 2, true)));
+    }
+
+    #[test]
+    fn hides_personal_data_by_default() {
+        let mut preferences = Preferences::default();
+        assert!(hidden(&preferences, "ccfind", false));
+        assert!(hidden(&preferences, "ccuser", true));
+        assert!(!hidden(&preferences, "ccuser", false));
+        assert!(!hidden(&preferences, "cclookup", false));
+
+        preferences.hidden.insert("all".to_string(), false);
+        preferences.hidden.insert("cclookup".to_string(), true);
+        assert!(!hidden(&preferences, "ccfind", false));
+        assert!(!hidden(&preferences, "ccuser", true));
+        assert!(hidden(&preferences, "cclookup", false));
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashSet;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serenity::model::interactions::application_command::ApplicationCommandInteraction;
+
+use crate::{data, error::BotResult, friends};
+
+lazy_static! {
+    static ref PRIVACYDB: sled::Db =
+        sled::open(std::env::var("PRIVACYDB").unwrap_or("./privacy.db".to_string()))
+            .expect("Expected to open the PRIVACYDB database, ./privacy.db by default");
+}
+
+pub fn load() {
+    lazy_static::initialize(&PRIVACYDB);
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    #[default]
+    Public,
+    // only visible in servers the user is in
+    Members,
+    Friends,
+    // like friends, but everyone else still counts the user anonymously
+    Counts,
+    // only ever counted anonymously, even by friends
+    Private,
+}
+
+impl Level {
+    pub const ALL: [Level; 5] = [
+        Level::Public,
+        Level::Members,
+        Level::Friends,
+        Level::Counts,
+        Level::Private,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Public => "public",
+            Level::Members => "members",
+            Level::Friends => "friends",
+            Level::Counts => "counts",
+            Level::Private => "private",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Level> {
+        Level::ALL.iter().copied().find(|l| l.name() == name)
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Level::Public => "Anyone can see your courses",
+            Level::Members => {
+                "Only your friends and people in servers you're in can see your courses; anyone else only counts you anonymously in class headcounts"
+            }
+            Level::Friends => "Only your friends can see or count you; nobody else knows you take a course",
+            Level::Counts => {
+                "Only your friends can see your courses; anyone else only counts you anonymously in class headcounts"
+            }
+            Level::Private => {
+                "Nobody, not even your friends, can see your courses; you only appear in anonymous class headcounts"
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Settings {
+    level: Level,
+    // servers the user chose not to be seen in at all
+    #[serde(default)]
+    hidden_guilds: HashSet<u64>,
+}
+
+// How the owner of some data and its viewer know each other.
+#[derive(Default)]
+struct Relation {
+    friends: bool,
+    // the owner is in the server the viewer is asking from
+    member: bool,
+}
+
+// Who is asking to see someone's data, and from where.
+pub struct Viewer {
+    pub uid: u64,
+    pub guild: Option<u64>,
+}
+
+impl Viewer {
+    pub fn of(command: &ApplicationCommandInteraction) -> Viewer {
+        Viewer {
+            uid: *command.user.id.as_u64(),
+            guild: command.guild_id.map(|g| *g.as_u64()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Visibility {
+    Full,
+    Count,
+    Hidden,
+}
+
+fn parse(bytes: &[u8]) -> BotResult<Settings> {
+    // settings used to be stored as a bare private flag
+    match bytes {
+        b"true" => Ok(Settings {
+            level: Level::Private,
+            ..Settings::default()
+        }),
+        b"false" => Ok(Settings::default()),
+        bytes => Ok(serde_json::from_slice(bytes)?),
+    }
+}
+
+pub fn store() -> data::Store<'static> {
+    store_in(&PRIVACYDB)
+}
+
+pub fn store_in(tree: &sled::Tree) -> data::Store<'_> {
+    data::Store {
+        name: "privacy",
+        tree,
+        read: |bytes| Ok(serde_json::to_value(parse(bytes)?)?),
+        forget: None,
+    }
+}
+
+fn get(uid: u64) -> BotResult<Settings> {
+    match PRIVACYDB.get(uid.to_be_bytes())? {
+        Some(ivec) => parse(&ivec),
+        None => Ok(Settings::default()),
+    }
+}
+
+fn put(uid: u64, settings: &Settings) -> BotResult<()> {
+    PRIVACYDB.insert(uid.to_be_bytes(), serde_json::to_vec(settings)?)?;
+    Ok(())
+}
+
+pub fn level(uid: u64) -> BotResult<Level> {
+    Ok(get(uid)?.level)
+}
+
+pub fn set_level(uid: u64, level: Level) -> BotResult<()> {
+    let mut settings = get(uid)?;
+    settings.level = level;
+    put(uid, &settings)
+}
+
+pub fn hidden_guilds(uid: u64) -> BotResult<usize> {
+    Ok(get(uid)?.hidden_guilds.len())
+}
+
+pub fn set_shown_in(uid: u64, guild: u64, shown: bool) -> BotResult<()> {
+    let mut settings = get(uid)?;
+    match shown {
+        true => settings.hidden_guilds.remove(&guild),
+        false => settings.hidden_guilds.insert(guild),
+    };
+    put(uid, &settings)
+}
+
+fn decide(settings: &Settings, owner: u64, viewer: &Viewer, relation: &Relation) -> Visibility {
+    if owner == viewer.uid {
+        return Visibility::Full;
+    }
+    match viewer.guild {
+        Some(guild) if settings.hidden_guilds.contains(&guild) => return Visibility::Hidden,
+        // in DMs there's no server to scope to, so only friends see anything
+        None if !relation.friends => return Visibility::Hidden,
+        _ => {}
+    }
+    // friends see everything short of a fully private schedule
+    if relation.friends && settings.level != Level::Private {
+        return Visibility::Full;
+    }
+    match settings.level {
+        Level::Public => Visibility::Full,
+        Level::Members if relation.member => Visibility::Full,
+        Level::Members | Level::Counts | Level::Private => Visibility::Count,
+        Level::Friends => Visibility::Hidden,
+    }
+}
+
+// How much of `owner`'s data `viewer` may see, where `member` is whether the
+// owner is in the server the viewer is asking from. Every command that shows
+// other users' data goes through this.
+pub fn visibility(owner: u64, viewer: &Viewer, member: bool) -> BotResult<Visibility> {
+    let settings = get(owner)?;
+    if owner == viewer.uid || settings.level == Level::Private {
+        return Ok(decide(&settings, owner, viewer, &Relation::default()));
+    }
+    let relation = Relation {
+        friends: friends::are_friends(owner, viewer.uid)?,
+        member: member && viewer.guild.is_some(),
+    };
+    Ok(decide(&settings, owner, viewer, &relation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decide, parse, Level, Relation, Viewer, Visibility};
+
+    #[test]
+    fn enforces_levels() {
+        let viewer = |guild| Viewer { uid: 2, guild };
+        let stranger = Relation::default();
+        let friend = Relation {
+            friends: true,
+            member: false,
+        };
+        let mut settings = parse(b"true").unwrap();
+        assert_eq!(settings.level, Level::Private);
+        assert_eq!(
+            decide(&settings, 1, &viewer(Some(10)), &stranger),
+            Visibility::Count
+        );
+        assert_eq!(
+            decide(&settings, 1, &viewer(Some(10)), &friend),
+            Visibility::Count
+        );
+        assert_eq!(
+            decide(&settings, 2, &viewer(Some(10)), &stranger),
+            Visibility::Full
+        );
+
+        settings = parse(b"false").unwrap();
+        assert_eq!(
+            decide(&settings, 1, &viewer(Some(11)), &stranger),
+            Visibility::Full
+        );
+
+        settings = parse(br#"{"level":"members"}"#).unwrap();
+        let member = Relation {
+            friends: false,
+            member: true,
+        };
+        assert_eq!(
+            decide(&settings, 1, &viewer(Some(10)), &member),
+            Visibility::Full
+        );
+        assert_eq!(
+            decide(&settings, 1, &viewer(Some(10)), &stranger),
+            Visibility::Count
+        );
+        assert_eq!(
+            decide(&settings, 1, &viewer(None), &friend),
+            Visibility::Full
+        );
+
+        settings = parse(br#"{"level":"friends"}"#).unwrap();
+        assert_eq!(
+            decide(&settings, 1, &viewer(Some(10)), &stranger),
+            Visibility::Hidden
+        );
+        assert_eq!(
+            decide(&settings, 1, &viewer(Some(10)), &friend),
+            Visibility::Full
+        );
+
+        settings = parse(br#"{"level":"counts"}"#).unwrap();
+        assert_eq!(settings.level, Level::Counts);
+        assert_eq!(
+            decide(&settings, 1, &viewer(Some(10)), &stranger),
+            Visibility::Count
+        );
+        assert_eq!(
+            decide(&settings, 1, &viewer(Some(10)), &friend),
+            Visibility::Full
+        );
+    }
+
+    #[test]
+    fn scopes_to_servers() {
+        let viewer = |guild| Viewer { uid: 2, guild };
+        let settings = parse(br#"{"level":"public","hidden_guilds":[11]}"#).unwrap();
+        let friend = Relation {
+            friends: true,
+            member: true,
+        };
+        assert_eq!(
+            decide(&settings, 1, &viewer(Some(10)), &Relation::default()),
+            Visibility::Full
+        );
+        assert_eq!(
+            decide(&settings, 1, &viewer(Some(11)), &friend),
+            Visibility::Hidden
+        );
+        assert_eq!(
+            decide(&settings, 1, &viewer(None), &Relation::default()),
+            Visibility::Hidden
+        );
+        assert_eq!(
+            decide(&settings, 1, &viewer(None), &friend),
+            Visibility::Full
+        );
+    }
+}
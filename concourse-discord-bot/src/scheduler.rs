@@ -0,0 +1,193 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, Duration as ChronoDuration, NaiveTime, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serenity::{builder::CreateEmbed, http::Http, model::id::UserId};
+
+use crate::{campus, data, error::BotResult, handlers};
+
+const TICK: Duration = Duration::from_secs(30);
+// after a longer outage, only catch up on this much of it
+const MAX_CATCH_UP: i64 = 15;
+const CURSOR_KEY: &[u8] = b"cursor";
+
+lazy_static! {
+    static ref SCHEDULEDB: sled::Db =
+        sled::open(std::env::var("SCHEDULEDB").unwrap_or("./schedule.db".to_string()))
+            .expect("Expected to open the SCHEDULEDB database, ./schedule.db by default");
+    // per-user settings are keyed by user id; the scheduler's own state lives
+    // in a separate tree so it never shows up among them
+    static ref STATE: sled::Tree = SCHEDULEDB.open_tree("state").unwrap();
+    static ref STARTED: AtomicBool = AtomicBool::new(false);
+}
+
+pub fn load() {
+    lazy_static::initialize(&SCHEDULEDB);
+    lazy_static::initialize(&STATE);
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Schedule {
+    pub reminder_minutes: Option<i64>,
+    // campus time of day the daily digest goes out
+    #[serde(default)]
+    pub digest: Option<NaiveTime>,
+}
+
+impl Schedule {
+    fn is_empty(&self) -> bool {
+        self.reminder_minutes.is_none() && self.digest.is_none()
+    }
+}
+
+pub fn store() -> data::Store<'static> {
+    store_in(&SCHEDULEDB)
+}
+
+pub fn store_in(tree: &sled::Tree) -> data::Store<'_> {
+    data::Store {
+        name: "notifications",
+        tree,
+        read: data::read_as::<Schedule>,
+        forget: None,
+    }
+}
+
+pub fn get(uid: u64) -> BotResult<Schedule> {
+    match SCHEDULEDB.get(uid.to_be_bytes())? {
+        Some(ivec) => Ok(serde_json::from_slice(&ivec)?),
+        None => Ok(Schedule::default()),
+    }
+}
+
+pub fn set(uid: u64, schedule: &Schedule) -> BotResult<()> {
+    match schedule.is_empty() {
+        true => SCHEDULEDB.remove(uid.to_be_bytes())?,
+        false => SCHEDULEDB.insert(uid.to_be_bytes(), serde_json::to_vec(schedule)?)?,
+    };
+    Ok(())
+}
+
+fn schedules() -> Vec<(u64, Schedule)> {
+    SCHEDULEDB
+        .iter()
+        .filter_map(|d| d.ok())
+        .filter_map(|(key, value)| {
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(key.get(..8)?);
+            Some((
+                u64::from_be_bytes(buf),
+                serde_json::from_slice(&value).ok()?,
+            ))
+        })
+        .collect()
+}
+
+// The last moment every notification up to has been sent for. Keeping it on
+// disk is what lets a restart pick up where the bot left off.
+fn cursor() -> Option<DateTime<Utc>> {
+    let ivec = STATE.get(CURSOR_KEY).ok()??;
+    serde_json::from_slice(&ivec).ok()
+}
+
+fn set_cursor(cursor: DateTime<Utc>) -> BotResult<()> {
+    STATE.insert(CURSOR_KEY, serde_json::to_vec(&cursor)?)?;
+    Ok(())
+}
+
+async fn send(http: &Http, uid: u64, embed: CreateEmbed) {
+    let sent = match UserId(uid).create_dm_channel(http).await {
+        Ok(channel) => channel
+            .send_message(http, |message| message.set_embed(embed))
+            .await
+            .map(|_| ()),
+        Err(why) => Err(why),
+    };
+    if let Err(why) = sent {
+        println!("Cannot send scheduled DM to {}: {}", uid, why);
+    }
+}
+
+// Where the next tick starts from: the last cursor, or no further back than
+// MAX_CATCH_UP.
+fn window_start(cursor: Option<DateTime<Utc>>, now: DateTime<Utc>) -> DateTime<Utc> {
+    cursor
+        .unwrap_or(now)
+        .max(now - ChronoDuration::minutes(MAX_CATCH_UP))
+}
+
+// Sends everything due since the last tick. Notifications are worked out from
+// the stored codes and the catalog each time, so edits apply right away. One
+// user's broken record doesn't hold up anyone else's.
+async fn tick(http: &Http) -> BotResult<()> {
+    let now = Utc::now();
+    let (from, to) = (
+        window_start(cursor(), now).with_timezone(&*campus::CAMPUS_TZ),
+        now.with_timezone(&*campus::CAMPUS_TZ),
+    );
+    for (uid, schedule) in schedules() {
+        if let Some(minutes) = schedule.reminder_minutes {
+            match handlers::reminders_due(uid, minutes, from, to) {
+                Ok(embeds) => {
+                    for embed in embeds {
+                        send(http, uid, embed).await;
+                    }
+                }
+                Err(why) => println!("Cannot work out reminders for {}: {}", uid, why),
+            }
+        }
+        if let Some(at) = schedule.digest {
+            match handlers::digests_due(uid, at, from, to) {
+                Ok(embeds) => {
+                    for embed in embeds {
+                        send(http, uid, embed).await;
+                    }
+                }
+                Err(why) => println!("Cannot work out the digest for {}: {}", uid, why),
+            }
+        }
+    }
+    set_cursor(now)
+}
+
+// Runs for as long as the bot does. `ready` fires again on reconnects, so
+// only the first call starts anything.
+pub fn start(http: Arc<Http>) {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TICK);
+        loop {
+            interval.tick().await;
+            if let Err(why) = tick(&http).await {
+                println!("Scheduler tick failed: {}", why);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::{window_start, MAX_CATCH_UP};
+
+    #[test]
+    fn limits_catch_up() {
+        let now = Utc.ymd(2021, 8, 25).and_hms(10, 0, 0);
+        assert_eq!(window_start(None, now), now);
+        let recent = now - Duration::seconds(30);
+        assert_eq!(window_start(Some(recent), now), recent);
+        let limit = now - Duration::minutes(MAX_CATCH_UP);
+        assert_eq!(window_start(Some(limit), now), limit);
+        assert_eq!(window_start(Some(now - Duration::hours(3)), now), limit);
+    }
+}
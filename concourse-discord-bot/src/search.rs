@@ -0,0 +1,77 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[derive(Default)]
+pub struct SearchIndex {
+    postings: BTreeMap<String, HashSet<i64>>,
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_uppercase())
+        .collect()
+}
+
+impl SearchIndex {
+    pub fn add(&mut self, id: i64, text: &str) {
+        let tokens = tokenize(text);
+        // the joined form lets "CS314" find "C S 314"
+        let joined = tokens.concat();
+        for token in tokens.into_iter().chain(std::iter::once(joined)) {
+            if !token.is_empty() {
+                self.postings.entry(token).or_default().insert(id);
+            }
+        }
+    }
+
+    // Every query token has to prefix-match some indexed token. Ids matching
+    // more tokens exactly come first, ties are broken by id.
+    pub fn query(&self, query: &str) -> Vec<i64> {
+        let mut matched: Option<HashSet<i64>> = None;
+        let mut exact: HashMap<i64, usize> = HashMap::new();
+        for token in tokenize(query) {
+            let mut ids = HashSet::new();
+            for (key, postings) in self
+                .postings
+                .range(token.clone()..)
+                .take_while(|(key, _)| key.starts_with(&token))
+            {
+                ids.extend(postings);
+                if *key == token {
+                    for id in postings {
+                        *exact.entry(*id).or_default() += 1;
+                    }
+                }
+            }
+            matched = Some(match matched {
+                Some(previous) => &previous & &ids,
+                None => ids,
+            });
+        }
+        let mut ids: Vec<i64> = matched.unwrap_or_default().into_iter().collect();
+        ids.sort_by_key(|id| (std::cmp::Reverse(exact.get(id).cloned().unwrap_or(0)), *id));
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SearchIndex;
+
+    #[test]
+    fn matches_prefixes_of_every_token() {
+        let mut index = SearchIndex::default();
+        index.add(1, "C S 314 DATA STRUCTURES");
+        index.add(1, "Smith");
+        index.add(2, "C S 429 COMP ORGANIZATN & ARCH");
+        index.add(3, "M 408D SEQ, SERIES, AND MULTIVAR CALC");
+        index.add(3, "Data-Smith");
+
+        assert_eq!(index.query("cs314"), vec![1]);
+        assert_eq!(index.query("c s"), vec![1, 2, 3]);
+        assert_eq!(index.query("data smith"), vec![1, 3]);
+        assert_eq!(index.query("struct smi"), vec![1]);
+        assert_eq!(index.query("physics"), Vec::<i64>::new());
+        assert_eq!(index.query(""), Vec::<i64>::new());
+    }
+}
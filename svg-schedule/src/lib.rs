@@ -1,6 +1,6 @@
-mod components;
-mod ui;
-mod utils;
+pub mod components;
+pub mod ui;
+pub mod utils;
 
 #[cfg(test)]
 mod tests {
@@ -18,7 +18,7 @@ const TIME_INTERVAL: i32 = 30;
 const TIME_SEGMENT_AMOUNT: i32 = (END_TIME - START_TIME) / TIME_INTERVAL;
 const VERTICAL_INTERVAL: f32 = (VIEWPORT_HEIGHT - PADDING * 6.6) / TIME_SEGMENT_AMOUNT as f32;
 
-pub fn render_command_text(mut doc: Document, text: &str) -> Document {
+pub fn render_command_text(doc: Document, text: &str) -> Document {
     doc.add(
         Text::default()
             .position(PADDING, PADDING + COMMAND_FONT_SIZE as f32)
@@ -29,7 +29,7 @@ pub fn render_command_text(mut doc: Document, text: &str) -> Document {
     )
 }
 
-pub fn render_name_text(mut doc: Document, name: &str) -> Document {
+pub fn render_name_text(doc: Document, name: &str) -> Document {
     doc.add(
         Text::default()
             .position(PADDING, PADDING * 3.0)
@@ -124,7 +124,7 @@ pub struct ClassInformation {
     pub room: Option<String>,
 }
 
-pub fn render_class(class_info: ClassInformation, mut doc: Document) -> Document {
+pub fn render_class(class_info: ClassInformation, doc: Document) -> Document {
     const OUTER_MARGIN: f32 = 2.5;
     const INNER_MARGIN: f32 = 7.5;
     let position = (
@@ -168,7 +168,7 @@ pub fn render_class(class_info: ClassInformation, mut doc: Document) -> Document
     )
     .add(
         Text::default()
-            .text(&class_info.instructor.as_ref().unwrap_or(&("–".to_string())))
+            .text(class_info.instructor.as_ref().unwrap_or(&("–".to_string())))
             .fill("white")
             .font_size(CLASS_FONT_SIZE)
             .position(
@@ -192,7 +192,7 @@ pub fn render_class(class_info: ClassInformation, mut doc: Document) -> Document
     )
     .add(
         Text::default()
-            .text(&class_info.room.as_ref().unwrap_or(&("–".to_string())))
+            .text(class_info.room.as_ref().unwrap_or(&("–".to_string())))
             .fill("white")
             .font_size(CLASS_FONT_SIZE)
             .position(
@@ -204,3 +204,53 @@ pub fn render_class(class_info: ClassInformation, mut doc: Document) -> Document
             .as_svg(),
     )
 }
+
+// `time` is (weekday index, start minute, end minute) like a class's. Windows
+// are drawn to the minute and cut off at the edges of the grid.
+pub fn render_free_window(time: (i32, i32, i32), doc: Document) -> Document {
+    const OUTER_MARGIN: f32 = 2.5;
+    const INNER_MARGIN: f32 = 7.5;
+    const FREE_FONT_SIZE: i32 = 14;
+    let (start, end) = (time.1.max(START_TIME), time.2.min(END_TIME));
+    if start >= end {
+        return doc;
+    }
+    let minute_height = VERTICAL_INTERVAL / TIME_INTERVAL as f32;
+    let position = (
+        time.0 as f32 * HORIZONTAL_INTERVAL + PADDING * 1.4 + OUTER_MARGIN,
+        (start - START_TIME) as f32 * minute_height + PADDING * 4.85 + OUTER_MARGIN,
+    );
+    let size = (
+        HORIZONTAL_INTERVAL - OUTER_MARGIN * 2.0,
+        (end - start) as f32 * minute_height - OUTER_MARGIN * 2.0,
+    );
+    let label = format!(
+        "Free {}:{:02}-{}:{:02}",
+        time.1 / 60,
+        time.1 % 60,
+        time.2 / 60,
+        time.2 % 60
+    );
+    doc.add(
+        Rectangle::default()
+            .position(position.0, position.1)
+            .size(size.0, size.1)
+            .fill("palegreen")
+            .stroke("seagreen")
+            .stroke_width(2.0)
+            .corner_radius(10.0)
+            .as_svg(),
+    )
+    .add(
+        Text::default()
+            .text(&label)
+            .fill("darkgreen")
+            .font_size(FREE_FONT_SIZE)
+            .position(
+                position.0 + INNER_MARGIN,
+                position.1 + INNER_MARGIN + FREE_FONT_SIZE as f32,
+            )
+            .font_weight("bold")
+            .as_svg(),
+    )
+}